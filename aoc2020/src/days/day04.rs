@@ -1,15 +1,12 @@
 use anyhow::{anyhow, Context, Result};
 use regex::Regex;
-use std::{
-  collections::HashMap,
-  fs::File,
-  io::{BufRead, BufReader},
-};
+use std::{collections::HashMap, str::FromStr};
 
 const INPUT: &str = "input/day04.txt";
 
 pub fn solution() {
-  let passes = parse_input();
+  let passes: Vec<Pass> =
+    input::parse_paragraphs(input::input_path(INPUT)).unwrap();
   let mut valid = 0;
   for Pass(pass) in passes.iter() {
     if pass.contains_key("byr")
@@ -33,34 +30,22 @@ pub fn solution() {
   println!("part2: {}", valid);
 }
 
-fn parse_input() -> Vec<Pass> {
-  let p = input::input_path(INPUT);
-  let f = File::open(p).unwrap();
-  let f = BufReader::new(f);
-  let mut passes = Vec::new();
-  let mut pass = HashMap::new();
-  for line in f.lines() {
-    let line = line.unwrap();
-    if line.is_empty() {
-      passes.push(Pass(pass));
-      pass = HashMap::new();
-      continue;
-    }
-    for pair in line.split_whitespace() {
-      let mut pair = pair.split(':');
-      let key = pair.next().unwrap();
-      let val = pair.next().unwrap();
+struct Pass(HashMap<String, String>);
+
+impl FromStr for Pass {
+  type Err = anyhow::Error;
+  fn from_str(s: &str) -> Result<Self> {
+    let mut pass = HashMap::new();
+    for pair in s.split_whitespace() {
+      let (key, val) = pair
+        .split_once(':')
+        .ok_or_else(|| anyhow!("'{}' isn't a key:value pair", pair))?;
       pass.insert(key.into(), val.into());
     }
+    Ok(Pass(pass))
   }
-  if !pass.is_empty() {
-    passes.push(Pass(pass));
-  }
-  passes
 }
 
-struct Pass(HashMap<String, String>);
-
 impl Pass {
   fn get(&self, key: &str) -> Result<&str> {
     self
@@ -74,21 +59,21 @@ impl Pass {
     // 'byr' is a number between 1920 and 2002
     let byr = self.get("byr")?;
     let byr: u16 = byr.parse().context("'byr' must be a number")?;
-    if byr < 1920 || byr > 2002 {
+    if !(1920..=2002).contains(&byr) {
       return Err(anyhow!("'byr' out of range"));
     }
 
     // 'iyr' is a number between 2010 and 2020
     let iyr = self.get("iyr")?;
     let iyr: u16 = iyr.parse().context("'iyr' must be a number")?;
-    if iyr < 2010 || iyr > 2020 {
+    if !(2010..=2020).contains(&iyr) {
       return Err(anyhow!("'iyr' out of range"));
     }
 
     // 'eyr' is a number between 2020 and 2030
     let eyr = self.get("eyr")?;
     let eyr: u16 = eyr.parse().context("'eyr' must be a number")?;
-    if eyr < 2020 || eyr > 2030 {
+    if !(2020..=2030).contains(&eyr) {
       return Err(anyhow!("'eyr' out of range"));
     }
 
@@ -114,12 +99,12 @@ fn validate_hgt(hgt: &str) -> Result<()> {
   let hgt: u16 = caps[1].parse()?;
   match &caps[2] {
     "in" => {
-      if hgt < 59 || hgt > 76 {
+      if !(59..=76).contains(&hgt) {
         return Err(anyhow!("'hgt' in inches out of range"));
       }
     }
     "cm" => {
-      if hgt < 150 || hgt > 193 {
+      if !(150..=193).contains(&hgt) {
         return Err(anyhow!("'hgt' in cm out of range"));
       }
     }
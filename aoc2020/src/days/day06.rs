@@ -1,38 +1,38 @@
-use input::input_path;
-use std::{
-  collections::HashMap,
-  fs::File,
-  io::{BufRead, BufReader},
-};
+use input::{input_path, parse_paragraphs};
+use std::{collections::HashMap, convert::Infallible, str::FromStr};
 
 const INPUT: &str = "input/day06.txt";
 //const INPUT: &str = "input/day06_test.txt";
 
 pub fn solution() {
-  let p = input_path(INPUT);
-  let f = File::open(p).unwrap();
-  let f = BufReader::new(f);
+  let groups: Vec<Group> = parse_paragraphs(input_path(INPUT)).unwrap();
   let mut part1 = 0;
   let mut part2 = 0;
-  let mut group = HashMap::new();
-  let mut members = 0;
-  for line in f.lines() {
-    let line = line.unwrap();
-    if line.is_empty() {
-      part1 += group.len();
-      part2 += group.values().filter(|c| **c == members).count();
-      // reset;
-      group = HashMap::new();
-      members = 0;
-      continue;
-    }
-    members += 1;
-    for c in line.chars() {
-      *group.entry(c).or_insert(0) += 1;
-    }
+  for Group { answers, members } in groups {
+    part1 += answers.len();
+    part2 += answers.values().filter(|c| **c == members).count();
   }
-  part1 += group.len();
-  part2 += group.values().filter(|c| **c == members).count();
   println!("part1: {}", part1);
   println!("part2: {}", part2);
 }
+
+/// How many people in a group answered yes to each question.
+struct Group {
+  answers: HashMap<char, usize>,
+  members: usize,
+}
+
+impl FromStr for Group {
+  type Err = Infallible;
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let mut answers = HashMap::new();
+    let mut members = 0;
+    for line in s.lines() {
+      members += 1;
+      for c in line.chars() {
+        *answers.entry(c).or_insert(0) += 1;
+      }
+    }
+    Ok(Group { answers, members })
+  }
+}
@@ -1,7 +1,5 @@
-use anyhow::{anyhow, Error, Result};
+use anyhow::{Error, Result};
 use input::{input_path, parse_lines};
-use once_cell::unsync::Lazy;
-use regex::Regex;
 use std::{
   collections::{HashMap, HashSet},
   str::FromStr,
@@ -17,9 +15,9 @@ pub fn solution() {
   // and a map of bag -> bags that bag holds.
   let mut holds: HashMap<&str, Vec<(usize, &str)>> = HashMap::new();
   for r in rules.iter() {
-    let holds_entry = holds.entry(&r.bag).or_insert(Vec::new());
-    for (n, bag) in r.contains.iter() {
-      contains.entry(bag).or_insert(Vec::new()).push(&r.bag);
+    let holds_entry = holds.entry(&r.bag).or_default();
+    for (n, bag) in r.contains.0.iter() {
+      contains.entry(bag).or_default().push(&r.bag);
       holds_entry.push((*n, bag))
     }
   }
@@ -62,32 +60,58 @@ pub fn solution() {
   }
 }
 
+input::scan! {
+  // posh crimson bags contain 2 mirrored tan bags, 1 faded red bag.
+  #[format("{bag} bags contain {contains}.")]
+  #[derive(Debug)]
+  struct Rule {
+    bag: String,
+    contains: Contents,
+  }
+}
+
+/// The bags a bag holds, and how many of each.
 #[derive(Debug)]
-struct Rule {
-  bag: String,
-  contains: Vec<(usize, String)>,
+struct Contents(Vec<(usize, String)>);
+
+input::scan! {
+  #[format("{n} {bag} bag")]
+  struct Held {
+    n: usize,
+    bag: String,
+  }
 }
 
-impl FromStr for Rule {
+impl FromStr for Contents {
   type Err = Error;
   fn from_str(s: &str) -> Result<Self, Self::Err> {
-    // posh crimson bags contain 2 mirrored tan bags, 1 faded red bag, 1 striped gray bag.
-    let rule_re = Lazy::new(|| {
-      Regex::new(r"^(?P<bag>.+) bags contain (?P<contents>.*).$").unwrap()
-    });
-    let contents_re =
-      Lazy::new(|| Regex::new(r"(?P<num>\d+) (?P<bag>[^\d]+) bag").unwrap());
-    let caps = rule_re
-      .captures(s)
-      .ok_or_else(|| anyhow!("malformed input"))?;
-    let mut rule = Rule {
-      bag: String::from(&caps["bag"]),
-      contains: Vec::new(),
-    };
-    for contents in contents_re.captures_iter(&caps["contents"]) {
-      let n = contents["num"].parse()?;
-      rule.contains.push((n, contents["bag"].into()))
+    if s == "no other bags" {
+      return Ok(Contents(Vec::new()));
     }
-    Ok(rule)
+    let mut contents = Vec::new();
+    for held in s.split(", ") {
+      let Held { n, bag } = held.trim_end_matches('s').parse()?;
+      contents.push((n, bag));
+    }
+    Ok(Contents(contents))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_rule_from_str() {
+    let line = "posh crimson bags contain 2 mirrored tan bags, 1 faded red bag.";
+    let rule: Rule = line.parse().unwrap();
+    assert_eq!(rule.bag, "posh crimson");
+    assert_eq!(
+      rule.contains.0,
+      vec![(2, "mirrored tan".to_string()), (1, "faded red".to_string())]
+    );
+    let rule: Rule = "faded blue bags contain no other bags.".parse().unwrap();
+    assert!(rule.contains.0.is_empty());
+    assert!("faded blue bags contain 2 red.".parse::<Rule>().is_err());
   }
 }
@@ -2,8 +2,6 @@ use std::{collections::HashSet, fmt, str::FromStr};
 
 use anyhow::anyhow;
 use input::{input_path, parse_lines};
-use once_cell::unsync::Lazy;
-use regex::Regex;
 
 const INPUT: &str = "input/day08.txt";
 
@@ -84,19 +82,38 @@ impl fmt::Display for Instruction {
   }
 }
 
+input::scan! {
+  #[format("{op} {num}")]
+  struct Line {
+    op: String,
+    num: i64,
+  }
+}
+
 impl FromStr for Instruction {
   type Err = anyhow::Error;
   fn from_str(s: &str) -> Result<Self, Self::Err> {
-    let re = Lazy::new(|| {
-      Regex::new(r"(?P<op>nop|acc|jmp) (?P<num>[+-]\d+)").unwrap()
-    });
-    let caps = re.captures(s).ok_or_else(|| anyhow!("malformed input"))?;
-    let num = i64::from_str(&caps["num"])?;
-    match &caps["op"] {
+    let Line { op, num } = s.parse()?;
+    match op.as_str() {
       "nop" => Ok(Instruction::Nop(num)),
       "jmp" => Ok(Instruction::Jmp(num)),
       "acc" => Ok(Instruction::Acc(num)),
-      _ => Err(anyhow!("unknown instruction")),
+      _ => Err(anyhow!("unknown instruction {:?}", op)),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_instruction_from_str() {
+    for line in ["nop +0", "acc -99", "jmp +4"] {
+      let instruction: Instruction = line.parse().unwrap();
+      assert_eq!(instruction.to_string(), line.replace('+', ""));
     }
+    assert!("nop".parse::<Instruction>().is_err());
+    assert!("mul +2".parse::<Instruction>().is_err());
   }
 }
@@ -1,126 +1,166 @@
-use std::{
-    collections::{HashMap, HashSet},
-    fmt::Display,
-    str::FromStr,
-};
+use std::collections::HashMap;
+
+use util::pos::{DigitGrid, Pos, PosMap, PosSet};
 
 const INPUT: &str = "input/day11.txt";
 
+/// Octopuses flash once their energy level goes above this...
+const THRESHOLD: u8 = 9;
+/// ...and then drop back to this.
+const RESET: u8 = 0;
+
 pub fn solution() {
-    let mut octos: Octos = input::parse(INPUT);
-    let mut part1 = 0;
-    for _ in 0..100 {
-        part1 += octos.step();
-    }
+    let octos = Octos::from(input::parse::<DigitGrid<u8>, _>(INPUT));
+    let mut part1 = octos.clone();
+    let part1: usize = (0..100).map(|_| part1.step()).sum();
     println!("Part1: {}", part1);
-    while octos.step() != octos.len() {}
-    println!("Part2: {}", octos.counter);
+    let analysis = octos.analyse(1_000_000);
+    println!("Part2: {}", analysis.first_sync.unwrap());
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Octos {
-    octos: HashMap<Pos, u8>,
-    counter: usize,
+    grid: DigitGrid<u8>,
+    steps: usize,
+}
+
+/// What happened while running the simulation to a repeated state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Analysis {
+    /// The first step on which every octopus flashed.
+    first_sync: Option<usize>,
+    /// The repeating part of the simulation, if one was found.
+    cycle: Option<Cycle>,
+}
+
+/// The state after step `start` recurs every `period` steps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Cycle {
+    start: usize,
+    period: usize,
+}
+
+impl From<DigitGrid<u8>> for Octos {
+    fn from(grid: DigitGrid<u8>) -> Self {
+        Octos { grid, steps: 0 }
+    }
 }
 
 impl Octos {
+    /// Runs a single step, returning the number of octopuses that flashed.
     fn step(&mut self) -> usize {
-        // First, the energy level of each octopus increases by 1.
-
-        // Then, any octopus with an energy level greater than 9 flashes.
-        // This increases the energy level of all adjacent octopuses by 1,
-        // including octopuses that are diagonally adjacent. If this causes
-        // an octopus to have an energy level greater than 9, it also flashes.
-        // This process continues as long as new octopuses keep having their
-        // energy level increased beyond 9. (An octopus can only flash at most
-        // once per step.)
-
-        // Finally, any octopus that flashed during this step has its energy
-        // level set to 0, as it used all of its energy to flash.
-        let mut flashes = HashSet::new();
-        let mut stack: Vec<Pos> = self.octos.keys().copied().collect();
-        while let Some(p) = stack.pop() {
-            let v = self.octos.get_mut(&p).unwrap();
-            *v += 1;
-            if *v > 9 && !flashes.contains(&p) {
-                flashes.insert(p);
-                for n in p.neighbors() {
-                    if self.octos.contains_key(&n) {
-                        stack.push(n);
-                    }
-                }
+        self.steps += 1;
+        cascade(&mut self.grid.0, THRESHOLD, RESET).len()
+    }
+
+    /// Steps until a state repeats or `max_steps` have been run, noting the
+    /// first step on which everything flashed.
+    fn analyse(mut self, max_steps: usize) -> Analysis {
+        let order: Vec<Pos> = {
+            let mut order: Vec<Pos> = self.grid.0.keys().copied().collect();
+            order.sort();
+            order
+        };
+        let state = |grid: &PosMap<u8>| -> Vec<u8> {
+            order.iter().map(|p| grid[p]).collect()
+        };
+
+        let mut seen = HashMap::from([(state(&self.grid.0), self.steps)]);
+        let mut first_sync = None;
+        while self.steps < max_steps {
+            if self.step() == order.len() && first_sync.is_none() {
+                first_sync = Some(self.steps);
+            }
+            if let Some(start) = seen.insert(state(&self.grid.0), self.steps) {
+                let cycle = Cycle {
+                    start,
+                    period: self.steps - start,
+                };
+                return Analysis {
+                    first_sync,
+                    cycle: Some(cycle),
+                };
             }
         }
-
-        for p in flashes.iter() {
-            self.octos.entry(*p).and_modify(|v| *v = 0);
+        Analysis {
+            first_sync,
+            cycle: None,
         }
-        self.counter += 1;
-        flashes.len()
     }
+}
 
-    #[allow(dead_code)]
-    fn view(&self, n: i64) -> OctosView {
-        let mut rows = Vec::new();
-        for y in 0..n {
-            let mut row = Vec::new();
-            for x in 0..n {
-                row.push(*self.octos.get(&Pos(x, y)).unwrap());
-            }
-            rows.push(row);
+/// Raises every cell by one. Any cell that goes above `threshold` flashes,
+/// raising all eight of its neighbours by one, which may make them flash in
+/// turn. Each cell flashes at most once, and every cell that flashed is set
+/// to `reset` at the end. Returns the cells that flashed.
+fn cascade(grid: &mut PosMap<u8>, threshold: u8, reset: u8) -> PosSet {
+    let mut flashes = PosSet::new();
+    let mut stack: Vec<Pos> = grid.keys().copied().collect();
+    while let Some(p) = stack.pop() {
+        let v = grid.get_mut(&p).unwrap();
+        *v = v.saturating_add(1);
+        if *v > threshold && flashes.insert(p) {
+            stack.extend(p.neighbors().iter().filter(|n| grid.contains_key(n)));
         }
-        OctosView(rows)
     }
-
-    fn len(&self) -> usize {
-        self.octos.len()
+    for p in flashes.iter() {
+        grid.insert(*p, reset);
     }
+    flashes
 }
 
-struct OctosView(Vec<Vec<u8>>);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-impl Display for OctosView {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let OctosView(rows) = self;
-        for row in rows {
-            for o in row {
-                write!(f, "{}", o)?;
-            }
-            writeln!(f)?;
-        }
-        Ok(())
+    fn example() -> Octos {
+        Octos::from(input::parse::<DigitGrid<u8>, _>("input/day11_test.txt"))
     }
-}
 
-#[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
-struct Pos(i64, i64);
-
-impl Pos {
-    fn neighbors(&self) -> [Pos; 8] {
-        let &Pos(x, y) = self;
-        [
-            Pos(x + 1, y),
-            Pos(x + 1, y + 1),
-            Pos(x, y + 1),
-            Pos(x - 1, y + 1),
-            Pos(x - 1, y),
-            Pos(x - 1, y - 1),
-            Pos(x, y - 1),
-            Pos(x + 1, y - 1),
-        ]
+    #[test]
+    fn test_step() {
+        let mut octos = example();
+        let flashes: usize = (0..10).map(|_| octos.step()).sum();
+        assert_eq!(flashes, 204);
+        assert_eq!(
+            octos.grid.to_string(),
+            "\
+0481112976
+0031112009
+0041112504
+0081111406
+0099111306
+0093511233
+0442361130
+5532252350
+0532250600
+0032240000
+"
+        );
     }
-}
 
-impl FromStr for Octos {
-    type Err = ();
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut octos = HashMap::new();
-        for (y, l) in s.lines().enumerate() {
-            for (x, c) in l.chars().enumerate() {
-                let o = c.to_digit(10).unwrap();
-                octos.insert(Pos(x as i64, y as i64), o as u8);
-            }
-        }
-        Ok(Octos { octos, counter: 0 })
+    #[test]
+    fn test_analyse() {
+        let analysis = example().analyse(1000);
+        assert_eq!(analysis.first_sync, Some(195));
+        // Once synchronised, everyone counts up to the threshold together.
+        assert_eq!(
+            analysis.cycle,
+            Some(Cycle {
+                start: 195,
+                period: 10
+            })
+        );
+        assert_eq!(example().analyse(100).first_sync, None);
+    }
+
+    #[test]
+    fn test_cascade() {
+        let mut grid: PosMap<u8> =
+            [(Pos(0, 0), 2), (Pos(1, 0), 1), (Pos(5, 5), 0)].into();
+        let flashes = cascade(&mut grid, 2, 7);
+        assert_eq!(flashes, PosSet::from([Pos(0, 0), Pos(1, 0)]));
+        assert_eq!(grid[&Pos(0, 0)], 7);
+        assert_eq!(grid[&Pos(5, 5)], 1);
     }
 }
@@ -1,11 +1,14 @@
-use std::{collections::HashSet, fmt::Display, str::FromStr};
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::bail;
+use util::pos::{Pos, PosSet, Transform};
 
 const INPUT: &str = "input/day13.txt";
 
 pub fn solution() {
     let Instructions(mut points, folds) = input::parse(INPUT);
     for (i, f) in folds.into_iter().enumerate() {
-        points.fold(f);
+        points.fold(f).unwrap();
         if i == 0 {
             let part1 = points.0.len();
             println!("Part1: {}", part1);
@@ -14,9 +17,6 @@ pub fn solution() {
     println!("Part2:\n{}", points);
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-struct Pos(i64, i64);
-
 #[derive(Debug, Clone, Copy)]
 enum Fold {
     X(i64),
@@ -25,47 +25,38 @@ enum Fold {
 
 struct Instructions(Points, Vec<Fold>);
 
-struct Points(HashSet<Pos>);
+struct Points(PosSet);
 
 impl Points {
-    fn fold(&mut self, f: Fold) {
-        match f {
-            Fold::X(n) => self.fold_left(n),
-            Fold::Y(n) => self.fold_up(n),
-        }
-    }
-
-    fn fold_up(&mut self, n: i64) {
-        let mut mirror = Vec::new();
-        for p in self.0.iter() {
-            if p.1 > n {
-                mirror.push(*p);
-            }
-        }
-        for p in mirror {
-            self.0.remove(&p);
-            self.0.insert(Pos(p.0, n - (p.1 - n)));
-        }
-    }
-
-    fn fold_left(&mut self, n: i64) {
-        let mut mirror = Vec::new();
-        for p in self.0.iter() {
-            if p.0 > n {
-                mirror.push(*p);
-            }
-        }
-        for p in mirror {
-            self.0.remove(&p);
-            self.0.insert(Pos(n - (p.0 - n), p.1));
+    /// Folds the far side of the line over onto the near side. The puzzle
+    /// promises no dot ever sits on a fold line, so any that do are an error.
+    fn fold(&mut self, f: Fold) -> anyhow::Result<()> {
+        let on_line: Vec<Pos> = self
+            .0
+            .iter()
+            .filter(|p| match f {
+                Fold::X(n) => p.0 == n,
+                Fold::Y(n) => p.1 == n,
+            })
+            .copied()
+            .collect();
+        if !on_line.is_empty() {
+            bail!("{:?} would leave dots on the fold line: {:?}", f, on_line);
         }
+        let points = std::mem::take(&mut self.0);
+        self.0 = points.map_pos(|p| match f {
+            Fold::X(n) if p.0 > n => p.reflect_x(n),
+            Fold::Y(n) if p.1 > n => p.reflect_y(n),
+            _ => p,
+        });
+        Ok(())
     }
 }
 
 impl FromStr for Instructions {
     type Err = ();
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut points = HashSet::new();
+        let mut points = PosSet::new();
         let mut lines = s.lines();
         for l in lines.by_ref() {
             if l.is_empty() {
@@ -79,16 +70,22 @@ impl FromStr for Instructions {
     }
 }
 
+input::scan! {
+    #[format("fold along {axis}={n}")]
+    struct FoldLine {
+        axis: char,
+        n: i64,
+    }
+}
+
 impl FromStr for Fold {
-    type Err = ();
+    type Err = anyhow::Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let s = s.strip_prefix("fold along ").unwrap();
-        let (dim, n) = s.split_once('=').unwrap();
-        let n = n.parse().unwrap();
-        match dim {
-            "x" => Ok(Fold::X(n)),
-            "y" => Ok(Fold::Y(n)),
-            _ => panic!("unknown dim {}", dim),
+        let FoldLine { axis, n } = s.parse()?;
+        match axis {
+            'x' => Ok(Fold::X(n)),
+            'y' => Ok(Fold::Y(n)),
+            _ => bail!("unknown axis {}", axis),
         }
     }
 }
@@ -109,3 +106,57 @@ impl Display for Points {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+6,10
+0,14
+9,10
+0,3
+10,4
+4,11
+6,0
+6,12
+4,1
+0,13
+10,12
+3,4
+3,0
+8,4
+1,10
+2,14
+8,10
+9,0
+
+fold along y=7
+fold along x=5
+";
+
+    #[test]
+    fn test_example() {
+        let Instructions(mut points, folds) = EXAMPLE.parse().unwrap();
+        points.fold(folds[0]).unwrap();
+        assert_eq!(points.0.len(), 17);
+        points.fold(folds[1]).unwrap();
+        assert_eq!(points.0.len(), 16);
+        assert_eq!(points.to_string(), "#####\n#   #\n#   #\n#   #\n#####\n");
+    }
+
+    #[test]
+    fn test_fold_from_str() {
+        assert!(matches!("fold along x=5".parse(), Ok(Fold::X(5))));
+        assert!(matches!("fold along y=7\n".parse(), Ok(Fold::Y(7))));
+        assert!("fold along z=1".parse::<Fold>().is_err());
+        assert!("fold along x=".parse::<Fold>().is_err());
+    }
+
+    #[test]
+    fn test_fold_on_line() {
+        let mut points = Points(PosSet::from([Pos(1, 1), Pos(2, 7)]));
+        assert!(points.fold(Fold::Y(7)).is_err());
+        assert!(points.fold(Fold::X(3)).is_ok());
+    }
+}
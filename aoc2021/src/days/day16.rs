@@ -1,270 +1,482 @@
-use std::str::FromStr;
-
-use anyhow::anyhow;
+use std::{fmt::Display, str::FromStr};
 
 const INPUT: &str = "input/day16.txt";
 
 pub fn solution() {
-    let t: Transmission = input::parse(INPUT);
-    let outermost = Outermost::from(t);
-    let part1 = outermost.sum_versions();
+    let packet: Packet = input::parse(INPUT);
+    let part1 = packet.sum_versions();
     println!("Part1: {}", part1);
-    let part2 = outermost.val();
+    let part2 = packet.val();
     println!("Part2: {}", part2);
+    // The encoder may pad differently, but it must decode to the same thing.
+    let reencoded: Packet = packet.to_hex().unwrap().parse().unwrap();
+    assert_eq!(reencoded, packet, "BITS codec didn't round-trip");
 }
 
-#[derive(Debug)]
-enum Op {
-    Literal(u64),
-    Sum(Vec<usize>),
-    Mul(Vec<usize>),
-    Min(Vec<usize>),
-    Max(Vec<usize>),
-    Gt(Vec<usize>),
-    Lt(Vec<usize>),
-    Eq(Vec<usize>),
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Sum,
+    Mul,
+    Min,
+    Max,
+    Gt,
+    Lt,
+    Eq,
 }
 
-#[derive(Debug)]
-struct Packet {
-    version: u8,
-    op: Op,
+/// How an operator packet records the extent of its sub-packets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Length {
+    /// Length type ID 0: the total number of bits in the sub-packets.
+    Bits,
+    /// Length type ID 1: the number of immediate sub-packets.
+    Count,
 }
 
-#[derive(Debug)]
-struct Outermost {
-    store: Vec<Packet>,
-    packet: Packet,
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Body {
+    Literal(u64),
+    Operator {
+        op: Op,
+        length: Length,
+        packets: Vec<Packet>,
+    },
 }
 
-#[derive(Debug, Default)]
-struct Transmission {
-    bits: Vec<u8>,
-    cursor: usize,
-    store: Vec<Packet>,
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Packet {
+    pub version: u8,
+    pub body: Body,
 }
 
-struct Bits<'transmission>(&'transmission [u8]);
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// A character that isn't a hex digit.
+    Hex(char),
+    /// The transmission ended `want` bits into a field at bit `at`.
+    Truncated { at: usize, want: usize },
+    /// The type ID at bit `at` isn't one we know.
+    UnknownType { at: usize, type_id: u8 },
+    /// The sub-packets of the operator at bit `at` didn't take up the number
+    /// of bits it said they would.
+    Length { at: usize, want: usize, got: usize },
+    /// The operator at bit `at` has the wrong number of sub-packets.
+    Arity { at: usize, op: Op, count: usize },
+    /// A literal at bit `at` doesn't fit in a `u64`.
+    Overflow { at: usize },
+    /// A packet's `field` is `value`, which doesn't fit in the `bits` the
+    /// encoding has for it.
+    TooLarge {
+        field: &'static str,
+        value: usize,
+        bits: usize,
+    },
+}
 
-impl Transmission {
-    fn take(&mut self, n: usize) -> Bits {
-        let slice = &self.bits[self.cursor..self.cursor + n];
-        self.cursor += n;
-        Bits(slice)
+impl std::error::Error for Error {}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Hex(c) => write!(f, "invalid hex digit {:?}", c),
+            Error::Truncated { at, want } => {
+                write!(f, "truncated: wanted {} bits at bit {}", want, at)
+            }
+            Error::UnknownType { at, type_id } => {
+                write!(f, "unknown type ID {} at bit {}", type_id, at)
+            }
+            Error::Length { at, want, got } => write!(
+                f,
+                "sub-packets at bit {} took {} bits, expected {}",
+                at, got, want
+            ),
+            Error::Arity { at, op, count } => {
+                write!(f, "{:?} at bit {} has {} sub-packets", op, at, count)
+            }
+            Error::Overflow { at } => {
+                write!(f, "literal at bit {} overflows", at)
+            }
+            Error::TooLarge { field, value, bits } => {
+                write!(f, "{} {} doesn't fit in {} bits", field, value, bits)
+            }
+        }
     }
+}
 
-    fn parse(&mut self) -> Packet {
-        let version = u8::from(self.take(3));
-        let type_id = u8::from(self.take(3));
-        let op = match type_id {
-            0 => Op::Sum(self.parse_op()),
-            1 => Op::Mul(self.parse_op()),
-            2 => Op::Min(self.parse_op()),
-            3 => Op::Max(self.parse_op()),
-            4 => Op::Literal(self.parse_literal()),
-            5 => Op::Gt(self.parse_op()),
-            6 => Op::Lt(self.parse_op()),
-            7 => Op::Eq(self.parse_op()),
-            _ => panic!("unknown type ID {}", type_id),
-        };
-        Packet { version, op }
+impl Op {
+    fn from_type_id(type_id: u8) -> Option<Op> {
+        Some(match type_id {
+            0 => Op::Sum,
+            1 => Op::Mul,
+            2 => Op::Min,
+            3 => Op::Max,
+            5 => Op::Gt,
+            6 => Op::Lt,
+            7 => Op::Eq,
+            _ => return None,
+        })
     }
 
-    fn parse_literal(&mut self) -> u64 {
-        let mut bits = Vec::new();
-        loop {
-            let Bits(chunk) = self.take(5);
-            bits.extend_from_slice(&chunk[1..]);
-            if chunk[0] == 0 {
-                break;
-            }
+    fn type_id(&self) -> u8 {
+        match self {
+            Op::Sum => 0,
+            Op::Mul => 1,
+            Op::Min => 2,
+            Op::Max => 3,
+            Op::Gt => 5,
+            Op::Lt => 6,
+            Op::Eq => 7,
         }
-        u64::from(Bits(&bits))
     }
 
-    fn parse_op(&mut self) -> Vec<usize> {
-        let mut subs = Vec::new();
-        let length_type_id = u8::from(self.take(1));
-        match length_type_id {
-            0 => {
-                // If the length type ID is 0, then the next 15 bits are a number
-                // that represents the total length in bits of the sub-packets
-                // contained by this packet.
-                let subpacket_bits = usize::from(self.take(15));
-                let stop = self.cursor + subpacket_bits;
-                while self.cursor != stop {
-                    assert!(self.cursor < stop);
-                    let p = self.parse();
-                    subs.push(self.store(p));
-                }
-            }
-            _ => {
-                // If the length type ID is 1, then the next 11 bits are a number
-                // that represents the number of sub-packets immediately contained
-                // by this packet.
-                let num_subpackets = u64::from(self.take(11));
-                for _ in 0..num_subpackets {
-                    let p = self.parse();
-                    subs.push(self.store(p));
-                }
-            }
+    fn arity_ok(&self, count: usize) -> bool {
+        match self {
+            Op::Gt | Op::Lt | Op::Eq => count == 2,
+            _ => count > 0,
         }
-        subs
     }
 
-    fn store(&mut self, p: Packet) -> usize {
-        let id = self.store.len();
-        self.store.push(p);
-        id
+    /// The infix symbol for operators that are printed between their
+    /// operands.
+    fn infix(&self) -> Option<&'static str> {
+        match self {
+            Op::Mul => Some("*"),
+            Op::Gt => Some(">"),
+            Op::Lt => Some("<"),
+            Op::Eq => Some("=="),
+            _ => None,
+        }
     }
 }
 
-impl<'this> Outermost {
-    fn sum_versions(&self) -> u64 {
-        self.packet.version as u64
-            + self.store.iter().map(|p| p.version as u64).sum::<u64>()
+const LITERAL: u8 = 4;
+
+/// Reads big-endian bit fields out of a byte buffer.
+pub struct BitReader<'a> {
+    bytes: &'a [u8],
+    cursor: usize,
+}
+
+impl<'a> BitReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        BitReader { bytes, cursor: 0 }
     }
 
-    fn val(&self) -> u64 {
-        self.val_of(&self.packet)
+    /// Reads an `n`-bit field, where `n <= 64`.
+    pub fn read(&mut self, n: usize) -> Result<u64, Error> {
+        if self.cursor + n > self.bytes.len() * 8 {
+            return Err(Error::Truncated {
+                at: self.cursor,
+                want: n,
+            });
+        }
+        let mut v = 0;
+        for i in self.cursor..self.cursor + n {
+            let bit = (self.bytes[i / 8] >> (7 - i % 8)) & 1;
+            v = (v << 1) | bit as u64;
+        }
+        self.cursor += n;
+        Ok(v)
     }
 
-    fn val_of(&self, p: &Packet) -> u64 {
-        use Op::*;
-        match &p.op {
-            Literal(v) => *v,
-            Sum(ps) => self.val_iter(ps).sum(),
-            Mul(ps) => self.val_iter(ps).product(),
-            Min(ps) => self.val_iter(ps).min().unwrap(),
-            Max(ps) => self.val_iter(ps).max().unwrap(),
-            Gt(ps) => {
-                let (lhs, rhs) = self.two_vals(ps);
-                u64::from(lhs > rhs)
+    pub fn packet(&mut self) -> Result<Packet, Error> {
+        let at = self.cursor;
+        let version = self.read(3)? as u8;
+        let type_id = self.read(3)? as u8;
+        if type_id == LITERAL {
+            return Ok(Packet {
+                version,
+                body: Body::Literal(self.literal(at)?),
+            });
+        }
+        let op = Op::from_type_id(type_id)
+            .ok_or(Error::UnknownType { at, type_id })?;
+        let mut packets = Vec::new();
+        let length = if self.read(1)? == 0 {
+            let want = self.read(15)? as usize;
+            let start = self.cursor;
+            while self.cursor < start + want {
+                packets.push(self.packet()?);
             }
-            Lt(ps) => {
-                let (lhs, rhs) = self.two_vals(ps);
-                u64::from(lhs < rhs)
+            let got = self.cursor - start;
+            if got != want {
+                return Err(Error::Length { at, want, got });
             }
-            Eq(ps) => {
-                let (lhs, rhs) = self.two_vals(ps);
-                u64::from(lhs == rhs)
+            Length::Bits
+        } else {
+            for _ in 0..self.read(11)? {
+                packets.push(self.packet()?);
             }
+            Length::Count
+        };
+        if !op.arity_ok(packets.len()) {
+            return Err(Error::Arity {
+                at,
+                op,
+                count: packets.len(),
+            });
         }
+        Ok(Packet {
+            version,
+            body: Body::Operator {
+                op,
+                length,
+                packets,
+            },
+        })
     }
 
-    fn val_iter(
-        &'this self,
-        ps: &'this [usize],
-    ) -> impl Iterator<Item = u64> + 'this {
-        ps.iter().map(|p| self.val_of(&self.store[*p]))
+    fn literal(&mut self, at: usize) -> Result<u64, Error> {
+        let mut n = 0u64;
+        loop {
+            let more = self.read(1)? == 1;
+            let group = self.read(4)?;
+            if n.leading_zeros() < 4 {
+                return Err(Error::Overflow { at });
+            }
+            n = (n << 4) | group;
+            if !more {
+                return Ok(n);
+            }
+        }
     }
+}
 
-    fn two_vals(&self, ps: &[usize]) -> (u64, u64) {
-        let lhs = self.val_of(&self.store[ps[0]]);
-        let rhs = self.val_of(&self.store[ps[1]]);
-        (lhs, rhs)
-    }
+/// Appends big-endian bit fields to a byte buffer.
+#[derive(Default)]
+pub struct BitWriter {
+    bytes: Vec<u8>,
+    len: usize,
 }
 
-impl From<Transmission> for Outermost {
-    fn from(mut t: Transmission) -> Self {
-        let p = t.parse();
-        Outermost {
-            store: t.store,
-            packet: p,
+impl BitWriter {
+    /// Writes the low `n` bits of `v`.
+    pub fn write(&mut self, v: u64, n: usize) {
+        for i in (0..n).rev() {
+            if self.len.is_multiple_of(8) {
+                self.bytes.push(0);
+            }
+            let bit = ((v >> i) & 1) as u8;
+            *self.bytes.last_mut().unwrap() |= bit << (7 - self.len % 8);
+            self.len += 1;
         }
     }
-}
 
-impl<'transmission> From<Bits<'transmission>> for u64 {
-    fn from(Bits(bits): Bits<'transmission>) -> Self {
-        let mut n = 0u64;
-        for b in bits {
-            n <<= 1;
-            n |= *b as u64;
+    /// Writes `value` as an `n` bit field, or fails if it doesn't fit.
+    fn field(
+        &mut self,
+        field: &'static str,
+        value: usize,
+        bits: usize,
+    ) -> Result<(), Error> {
+        if value >> bits != 0 {
+            return Err(Error::TooLarge { field, value, bits });
         }
-        n
+        self.write(value as u64, bits);
+        Ok(())
+    }
+
+    pub fn packet(&mut self, p: &Packet) -> Result<(), Error> {
+        self.field("version", p.version as usize, 3)?;
+        match &p.body {
+            Body::Literal(n) => {
+                self.write(LITERAL as u64, 3);
+                let groups = (64 - n.leading_zeros() as usize).div_ceil(4);
+                for i in (0..groups.max(1)).rev() {
+                    self.write(u64::from(i > 0), 1);
+                    self.write(n >> (4 * i), 4);
+                }
+            }
+            Body::Operator {
+                op,
+                length,
+                packets,
+            } => {
+                self.write(op.type_id() as u64, 3);
+                match length {
+                    Length::Bits => {
+                        let mut subs = BitWriter::default();
+                        for p in packets {
+                            subs.packet(p)?;
+                        }
+                        self.write(0, 1);
+                        self.field("sub-packet length", subs.len, 15)?;
+                        self.append(&subs);
+                    }
+                    Length::Count => {
+                        self.write(1, 1);
+                        self.field("sub-packet count", packets.len(), 11)?;
+                        for p in packets {
+                            self.packet(p)?;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
     }
-}
 
-impl<'transmission> From<Bits<'transmission>> for usize {
-    fn from(bits: Bits<'transmission>) -> Self {
-        u64::from(bits) as usize
+    fn append(&mut self, other: &BitWriter) {
+        let mut r = BitReader::new(&other.bytes);
+        for _ in 0..other.len {
+            self.write(r.read(1).unwrap(), 1);
+        }
     }
 }
 
-impl<'transmission> From<Bits<'transmission>> for u8 {
-    fn from(bits: Bits<'transmission>) -> Self {
-        u64::from(bits) as u8
+impl Packet {
+    pub fn sum_versions(&self) -> u64 {
+        let subs = match &self.body {
+            Body::Literal(_) => 0,
+            Body::Operator { packets, .. } => {
+                packets.iter().map(Packet::sum_versions).sum()
+            }
+        };
+        self.version as u64 + subs
+    }
+
+    pub fn val(&self) -> u64 {
+        let (op, packets) = match &self.body {
+            Body::Literal(v) => return *v,
+            Body::Operator { op, packets, .. } => (op, packets),
+        };
+        let mut vals = packets.iter().map(Packet::val);
+        match op {
+            Op::Sum => vals.sum(),
+            Op::Mul => vals.product(),
+            Op::Min => vals.min().unwrap(),
+            Op::Max => vals.max().unwrap(),
+            Op::Gt | Op::Lt | Op::Eq => {
+                let (lhs, rhs) = (vals.next().unwrap(), vals.next().unwrap());
+                u64::from(match op {
+                    Op::Gt => lhs > rhs,
+                    Op::Lt => lhs < rhs,
+                    _ => lhs == rhs,
+                })
+            }
+        }
+    }
+
+    /// Serialises the packet as hex, zero-padded to a whole byte.
+    /// Encodes the packet, failing if a field is too large for its slot.
+    pub fn to_hex(&self) -> Result<String, Error> {
+        let mut w = BitWriter::default();
+        w.packet(self)?;
+        Ok(w.bytes.iter().map(|b| format!("{:02X}", b)).collect())
+    }
+
+    fn is_infix(&self) -> bool {
+        matches!(&self.body, Body::Operator { op, .. } if op.infix().is_some())
     }
 }
 
-const BITS: &[&[u8]] = &[
-    &[0, 0, 0, 0],
-    &[0, 0, 0, 1],
-    &[0, 0, 1, 0],
-    &[0, 0, 1, 1],
-    &[0, 1, 0, 0],
-    &[0, 1, 0, 1],
-    &[0, 1, 1, 0],
-    &[0, 1, 1, 1],
-    &[1, 0, 0, 0],
-    &[1, 0, 0, 1],
-    &[1, 0, 1, 0],
-    &[1, 0, 1, 1],
-    &[1, 1, 0, 0],
-    &[1, 1, 0, 1],
-    &[1, 1, 1, 0],
-    &[1, 1, 1, 1],
-];
-
-impl FromStr for Transmission {
-    type Err = anyhow::Error;
+impl FromStr for Packet {
+    type Err = Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut bits = Vec::new();
-        for c in s.trim().chars() {
-            let d = c
-                .to_digit(16)
-                .ok_or_else(|| anyhow!("unknown digit {}", c))?
-                as usize;
-            bits.extend(BITS[d]);
+        let digits = s
+            .trim()
+            .chars()
+            .map(|c| c.to_digit(16).map(|d| d as u8).ok_or(Error::Hex(c)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let bytes: Vec<u8> = digits
+            .chunks(2)
+            .map(|d| d[0] << 4 | d.get(1).unwrap_or(&0))
+            .collect();
+        BitReader::new(&bytes).packet()
+    }
+}
+
+/// Renders the packet as an expression, e.g. `sum(min(1, 2), 3 * 5)`.
+impl Display for Packet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (op, packets) = match &self.body {
+            Body::Literal(v) => return write!(f, "{}", v),
+            Body::Operator { op, packets, .. } => (op, packets),
+        };
+        if let Some(sym) = op.infix() {
+            for (i, p) in packets.iter().enumerate() {
+                if i > 0 {
+                    write!(f, " {} ", sym)?;
+                }
+                if p.is_infix() {
+                    write!(f, "({})", p)?;
+                } else {
+                    write!(f, "{}", p)?;
+                }
+            }
+            return Ok(());
         }
-        Ok(Transmission {
-            bits,
-            ..Transmission::default()
-        })
+        let name = match op {
+            Op::Sum => "sum",
+            Op::Min => "min",
+            Op::Max => "max",
+            Op::Mul | Op::Gt | Op::Lt | Op::Eq => unreachable!("infix"),
+        };
+        write!(f, "{}(", name)?;
+        for (i, p) in packets.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", p)?;
+        }
+        write!(f, ")")
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use util::rng::Lcg;
+
+    const EXAMPLES: &[&str] = &[
+        "D2FE28",
+        "38006F45291200",
+        "EE00D40C823060",
+        "8A004A801A8002F478",
+        "620080001611562C8802118E34",
+        "C0015000016115A2E0802F182340",
+        "A0016C880162017C3686B18A3D4780",
+        "C200B40A82",
+        "04005AC33890",
+        "880086C3E88112",
+        "CE00C43D881120",
+        "D8005AC2A8F0",
+        "F600BC2D8F",
+        "9C005AC2F8F0",
+        "9C0141080250320F1802104A08",
+    ];
 
     fn val(s: &str) -> u64 {
-        let t: Transmission = s.parse().unwrap();
-        let o = Outermost::from(t);
-        o.val()
+        s.parse::<Packet>().unwrap().val()
     }
 
     #[test]
-    fn test_take() {
-        let mut t: Transmission = "F0".parse().unwrap();
-        assert!(matches!(t.take(4), Bits(&[1, 1, 1, 1])));
-        assert!(matches!(t.take(4), Bits(&[0, 0, 0, 0])));
+    fn test_read() {
+        let mut r = BitReader::new(&[0xF0]);
+        assert_eq!(r.read(4), Ok(0xF));
+        assert_eq!(r.read(4), Ok(0));
+        assert_eq!(r.read(1), Err(Error::Truncated { at: 8, want: 1 }));
     }
 
     #[test]
-    fn test_conversion() {
-        assert_eq!(u64::from(Bits(&[1, 0, 1, 0, 1, 0])), 42);
+    fn test_parse_literal() {
+        let p: Packet = "D2FE28".parse().unwrap();
+        assert_eq!(
+            p,
+            Packet {
+                version: 6,
+                body: Body::Literal(2021)
+            }
+        );
     }
 
     #[test]
-    fn test_parse_literal() {
-        let mut t = Transmission {
-            bits: vec![1, 0, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 1, 0, 1, 0, 0, 0],
-            ..Transmission::default()
-        };
-        assert_eq!(t.parse_literal(), 2021);
+    fn test_sum_versions() {
+        let sum = |s: &str| s.parse::<Packet>().unwrap().sum_versions();
+        assert_eq!(sum("8A004A801A8002F478"), 16);
+        assert_eq!(sum("620080001611562C8802118E34"), 12);
+        assert_eq!(sum("C0015000016115A2E0802F182340"), 23);
+        assert_eq!(sum("A0016C880162017C3686B18A3D4780"), 31);
     }
 
     #[test]
@@ -278,4 +490,112 @@ mod tests {
         assert_eq!(val("9C005AC2F8F0"), 0);
         assert_eq!(val("9C0141080250320F1802104A08"), 1);
     }
+
+    #[test]
+    fn test_display() {
+        let show = |s: &str| s.parse::<Packet>().unwrap().to_string();
+        assert_eq!(show("C200B40A82"), "sum(1, 2)");
+        assert_eq!(show("04005AC33890"), "6 * 9");
+        assert_eq!(show("880086C3E88112"), "min(7, 8, 9)");
+        assert_eq!(show("9C0141080250320F1802104A08"), "sum(1, 3) == (2 * 2)");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for hex in EXAMPLES {
+            let p: Packet = hex.parse().unwrap();
+            assert_eq!(p.to_hex().unwrap(), *hex);
+            assert_eq!(p.to_hex().unwrap().parse(), Ok(p));
+        }
+        let p: Packet = input::parse(INPUT);
+        assert_eq!(p.to_hex().unwrap().parse(), Ok(p));
+    }
+
+    #[test]
+    fn test_too_large() {
+        let one = Packet {
+            version: 0,
+            body: Body::Literal(1),
+        };
+        let sum = |length, n| Packet {
+            version: 0,
+            body: Body::Operator {
+                op: Op::Sum,
+                length,
+                packets: vec![one.clone(); n],
+            },
+        };
+        // Each literal takes 11 bits.
+        assert!(sum(Length::Bits, 2978).to_hex().is_ok());
+        assert_eq!(
+            sum(Length::Bits, 2979).to_hex(),
+            Err(Error::TooLarge {
+                field: "sub-packet length",
+                value: 2979 * 11,
+                bits: 15
+            })
+        );
+        let p = sum(Length::Count, 2047);
+        assert_eq!(p.to_hex().unwrap().parse(), Ok(p));
+        assert_eq!(
+            sum(Length::Count, 2048).to_hex(),
+            Err(Error::TooLarge {
+                field: "sub-packet count",
+                value: 2048,
+                bits: 11
+            })
+        );
+        let p = Packet { version: 8, ..one };
+        assert!(p.to_hex().is_err());
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!("D2FG28".parse::<Packet>(), Err(Error::Hex('G')));
+        assert_eq!(
+            "D2FE".parse::<Packet>(),
+            Err(Error::Truncated { at: 16, want: 1 })
+        );
+        // A comparison needs exactly two sub-packets.
+        let hex = Packet {
+            version: 0,
+            body: Body::Operator {
+                op: Op::Gt,
+                length: Length::Count,
+                packets: vec![Packet {
+                    version: 0,
+                    body: Body::Literal(1),
+                }],
+            },
+        }
+        .to_hex()
+        .unwrap();
+        assert_eq!(
+            hex.parse::<Packet>(),
+            Err(Error::Arity {
+                at: 0,
+                op: Op::Gt,
+                count: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_fuzz() {
+        // Every prefix of a valid transmission must fail cleanly, as must a
+        // stream of pseudo-random garbage.
+        for hex in EXAMPLES {
+            for end in 0..hex.len() - 2 {
+                assert!(hex[..end].parse::<Packet>().is_err(), "{}", end);
+            }
+        }
+        let mut rng = Lcg::new(0x2021);
+        for _ in 0..10_000 {
+            let hex: String =
+                (0..16).map(|_| format!("{:X}", rng.below(16))).collect();
+            if let Ok(p) = hex.parse::<Packet>() {
+                assert_eq!(p.to_hex().unwrap().parse(), Ok(p));
+            }
+        }
+    }
 }
@@ -1,120 +1,262 @@
-use std::{cmp::Ordering, str::FromStr};
+use std::ops::RangeInclusive;
 
+use anyhow::anyhow;
 use util::pos::Pos;
 
 const INPUT: &str = "input/day17.txt";
 
 pub fn solution() {
     let target: Target = input::parse(INPUT);
+    let velocities = target.velocities().unwrap();
+    let part1 = velocities.iter().map(|v| apex(v.1)).max().unwrap();
+    let part2 = velocities.len();
+    println!("Part1: {}\nPart2: {}", part1, part2);
+}
+
+input::scan! {
+    #[format("target area: x={xmin}..{xmax}, y={ymin}..{ymax}")]
+    #[derive(Debug, Clone, Copy)]
+    struct Target {
+        xmin: i64,
+        xmax: i64,
+        ymin: i64,
+        ymax: i64,
+    }
+}
+
+/// The steps (counting from 1) during which one coordinate of the probe is
+/// within the target. `last` is `None` if it stays there forever.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Steps {
+    first: i64,
+    last: Option<i64>,
+}
+
+impl Steps {
+    fn new(first: i64, last: Option<i64>) -> Option<Steps> {
+        let first = first.max(1);
+        match last {
+            Some(last) if last < first => None,
+            _ => Some(Steps { first, last }),
+        }
+    }
 
-    let mut part1 = i64::MIN;
-    let mut part2 = 0;
-    for x in 0..target.xmax * 2 {
-        for y in target.ymin * 2..=-target.ymin * 2 {
-            let mut probe = Probe::new(Pos(x, y), target);
-            let mut maxy = i64::MIN;
-            loop {
-                match probe.step() {
-                    Status::OnTarget(pos) => {
-                        if pos.1 > maxy {
-                            maxy = pos.1;
-                        }
-                    }
-                    Status::Hit => {
-                        if maxy > part1 {
-                            part1 = maxy
-                        }
-                        part2 += 1;
-                        break;
-                    }
-                    Status::Overshot => {
-                        break;
-                    }
+    fn overlaps(&self, other: &Steps) -> bool {
+        let first = self.first.max(other.first);
+        match (self.last, other.last) {
+            (Some(a), Some(b)) => first <= a.min(b),
+            (Some(a), None) | (None, Some(a)) => first <= a,
+            (None, None) => true,
+        }
+    }
+}
+
+impl Target {
+    /// Every initial velocity that puts the probe inside the target after
+    /// some step, sorted by `(vx, vy)`.
+    ///
+    /// The x and y velocities are independent, so each axis is solved on
+    /// its own and the two are joined wherever their step ranges overlap.
+    fn velocities(&self) -> anyhow::Result<Vec<Pos>> {
+        let xs = self.x_velocities();
+        let ys = self.y_velocities(&xs)?;
+        let mut velocities = Vec::new();
+        for (vx, x_steps) in &xs {
+            for (vy, y_steps) in &ys {
+                if y_steps.iter().any(|s| s.overlaps(x_steps)) {
+                    velocities.push(Pos(*vx, *vy));
                 }
             }
         }
+        Ok(velocities)
     }
 
-    println!("Part1: {}\nPart2: {}", part1, part2);
+    /// X velocities paired with the steps at which x is within the target.
+    fn x_velocities(&self) -> Vec<(i64, Steps)> {
+        // Drag pulls the probe towards zero, so it never moves more than
+        // |vx| in the direction it was launched.
+        (self.xmin.min(0)..=self.xmax.max(0))
+            .filter_map(|vx| {
+                // Mirror leftward launches so x only ever increases.
+                let steps = if vx < 0 {
+                    x_steps(-vx, -self.xmax..=-self.xmin)
+                } else {
+                    x_steps(vx, self.xmin..=self.xmax)
+                };
+                steps.map(|s| (vx, s))
+            })
+            .collect()
+    }
+
+    /// Y velocities paired with the (at most two) step ranges at which y is
+    /// within the target: one on the way up and one on the way down.
+    fn y_velocities(
+        &self,
+        xs: &[(i64, Steps)],
+    ) -> anyhow::Result<Vec<(i64, Vec<Steps>)>> {
+        let bound = self.ymin.abs().max(self.ymax.abs());
+        let vy_max = if (self.ymin..=self.ymax).contains(&0) {
+            // Every upward launch comes back through y=0 eventually, so the
+            // only limit is how long x stays within the target.
+            let mut last = 0;
+            for (_, s) in xs {
+                let l = s.last.ok_or_else(|| {
+                    anyhow!("target contains y=0 and a probe can stall in it")
+                })?;
+                last = last.max(l);
+            }
+            bound + last
+        } else {
+            bound
+        };
+        Ok((self.ymin.min(0)..=vy_max)
+            .map(|vy| (vy, y_steps(vy, self.ymin..=self.ymax)))
+            .filter(|(_, s)| !s.is_empty())
+            .collect())
+    }
 }
 
-struct Probe {
-    pos: Pos,
-    velocity: Pos,
-    target: Target,
+/// The nth triangular number, extended to negative `n` so that
+/// `tri(n) == tri(-1 - n)`.
+fn tri(n: i64) -> i64 {
+    n * (n + 1) / 2
 }
 
-enum Status {
-    OnTarget(Pos),
-    Hit,
-    Overshot,
+/// The largest `k >= 0` with `tri(k) <= t`, or `None` if `t` is negative.
+fn tri_floor(t: i64) -> Option<i64> {
+    (t >= 0).then(|| ((8 * t + 1).isqrt() - 1) / 2)
 }
 
-impl Probe {
-    fn new(velocity: Pos, target: Target) -> Self {
-        Probe {
-            pos: Pos::default(),
-            velocity,
-            target,
-        }
+/// The smallest `k >= 0` with `tri(k) >= t`.
+fn tri_ceil(t: i64) -> i64 {
+    if t <= 0 {
+        0
+    } else {
+        tri_floor(t - 1).unwrap() + 1
     }
+}
 
-    fn step(&mut self) -> Status {
-        self.pos += self.velocity;
-        let Pos(x, y) = self.velocity;
-        let x = match x.cmp(&0) {
-            Ordering::Less => x + 1,
-            Ordering::Equal => 0,
-            Ordering::Greater => x - 1,
-        };
-        self.velocity = Pos(x, y - 1);
-        if self.target.contains(self.pos) {
-            return Status::Hit;
-        }
-        if self.target.overshot(self.pos) {
-            return Status::Overshot;
-        }
-        Status::OnTarget(self.pos)
+/// The highest point reached when launched with y velocity `vy`.
+fn apex(vy: i64) -> i64 {
+    if vy > 0 {
+        tri(vy)
+    } else {
+        0
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-struct Target {
-    xmin: i64,
-    xmax: i64,
-    ymin: i64,
-    ymax: i64,
+/// The steps at which a probe launched with non-negative `vx` has its x
+/// coordinate in `range`.
+///
+/// After `n <= vx` steps, `x = tri(vx) - tri(vx - n)`, after which the probe
+/// has stalled at `tri(vx)`.
+fn x_steps(vx: i64, range: RangeInclusive<i64>) -> Option<Steps> {
+    let (&lo, &hi) = (range.start(), range.end());
+    let stall = tri(vx);
+    if hi < 0 || stall < lo {
+        return None;
+    }
+    // x >= lo  <=>  tri(vx - n) <= stall - lo
+    let first = vx - tri_floor(stall - lo).unwrap();
+    // x <= hi  <=>  tri(vx - n) >= stall - hi
+    let last = (stall > hi).then(|| vx - tri_ceil(stall - hi));
+    Steps::new(first, last)
 }
 
-impl Target {
-    fn contains(&self, p: Pos) -> bool {
-        (self.xmin..=self.xmax).contains(&p.0)
-            && (self.ymin..=self.ymax).contains(&p.1)
+/// The steps at which a probe launched with `vy` has its y coordinate in
+/// `range`.
+///
+/// After `n` steps, `y = tri(vy) - tri(vy - n)`, which holds on both sides
+/// of the apex thanks to `tri`'s symmetry.
+fn y_steps(vy: i64, range: RangeInclusive<i64>) -> Vec<Steps> {
+    let (&lo, &hi) = (range.start(), range.end());
+    // y >= lo  <=>  -1 - k <= vy - n <= k
+    let Some(k) = tri_floor(tri(vy) - lo) else {
+        return Vec::new();
+    };
+    // y <= hi  <=>  vy - n >= j || vy - n <= -1 - j
+    let j = tri_ceil(tri(vy) - hi);
+    [
+        Steps::new(vy - k, Some(vy - j)),
+        Steps::new(vy + 1 + j, Some(vy + 1 + k)),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(xmin: i64, xmax: i64, ymin: i64, ymax: i64) -> Target {
+        Target {
+            xmin,
+            xmax,
+            ymin,
+            ymax,
+        }
+    }
+
+    /// Steps the probe long enough to reach any of the test targets.
+    fn hits(t: &Target, Pos(mut vx, mut vy): Pos) -> bool {
+        let mut p = Pos::default();
+        for _ in 0..1000 {
+            p += Pos(vx, vy);
+            vx -= vx.signum();
+            vy -= 1;
+            if (t.xmin..=t.xmax).contains(&p.0)
+                && (t.ymin..=t.ymax).contains(&p.1)
+            {
+                return true;
+            }
+        }
+        false
     }
 
-    fn overshot(&self, p: Pos) -> bool {
-        // This is a hack and assumes the target is somewhere below!
-        p.0 > self.xmax || p.1 < self.ymin
+    fn brute_force(t: &Target) -> Vec<Pos> {
+        let b = 2 * [t.xmin, t.xmax, t.ymin, t.ymax]
+            .iter()
+            .map(|c| c.abs())
+            .max()
+            .unwrap();
+        let mut v = Vec::new();
+        for vx in -b..=b {
+            for vy in -b..=b {
+                if hits(t, Pos(vx, vy)) {
+                    v.push(Pos(vx, vy));
+                }
+            }
+        }
+        v
+    }
+
+    #[test]
+    fn test_example() {
+        let t: Target = "target area: x=20..30, y=-10..-5".parse().unwrap();
+        let v = t.velocities().unwrap();
+        assert_eq!(v.len(), 112);
+        assert_eq!(v.iter().map(|v| apex(v.1)).max(), Some(45));
+        assert!(v.contains(&Pos(6, 9)));
+    }
+
+    #[test]
+    fn test_quadrants() {
+        for t in [
+            target(20, 30, -10, -5),
+            target(-30, -20, -10, -5),
+            target(20, 30, 5, 10),
+            target(-30, -20, 5, 10),
+            target(-3, 4, -10, -5),
+            target(-3, 4, 5, 10),
+            target(16, 20, -4, 6),
+        ] {
+            assert_eq!(t.velocities().unwrap(), brute_force(&t), "{:?}", t);
+        }
     }
-}
 
-impl FromStr for Target {
-    type Err = ();
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        //target area: x=85..145, y=-163..-108
-        let s = s
-            .trim()
-            .strip_prefix("target area: x=")
-            .unwrap()
-            .replace(" y=", "");
-        let (x, y) = s.split_once(',').unwrap();
-        let (xmin, xmax) = x.split_once("..").unwrap();
-        let (ymin, ymax) = y.split_once("..").unwrap();
-        Ok(Target {
-            xmin: xmin.parse().unwrap(),
-            xmax: xmax.parse().unwrap(),
-            ymin: ymin.parse().unwrap(),
-            ymax: ymax.parse().unwrap(),
-        })
+    #[test]
+    fn test_stall_at_zero() {
+        assert!(target(-3, 4, -4, 6).velocities().is_err());
     }
 }
@@ -1,274 +1,261 @@
-use std::{fmt::Display, ops::Add, str::FromStr};
+use std::{fmt::Display, ops::Add, str::FromStr, thread};
+
+use anyhow::{anyhow, bail};
 
 const INPUT: &str = "input/day18.txt";
 
 pub fn solution() {
     let numbers: Vec<Number> = input::parse_lines(INPUT);
+    // Adding step by step rather than with `+` lets us count the actions.
+    let (mut explodes, mut splits) = (0, 0);
     let sum: Number = numbers
         .clone()
         .into_iter()
-        .reduce(|acc, num| acc + num)
+        .reduce(|acc, num| {
+            let mut trace = Number::pair(acc, num).trace();
+            for (action, _) in trace.by_ref() {
+                match action {
+                    Action::Explode(..) => explodes += 1,
+                    Action::Split(_) => splits += 1,
+                }
+            }
+            trace.0
+        })
         .unwrap();
-    let pair = Pair::from(sum);
-    let part1 = pair.magnitude();
+    let part1 = sum.magnitude();
     println!("Part1: {}", part1);
-
-    let mut part2 = 0;
-    for (i, n) in numbers.iter().enumerate() {
-        for (j, m) in numbers.iter().enumerate() {
-            if i == j {
-                continue;
-            }
-            let sum: Number = n.clone() + m.clone();
-            let mag = Pair::from(sum).magnitude();
-            if mag > part2 {
-                part2 = mag;
-            }
-        }
-    }
+    println!("Reducing took {} explodes and {} splits", explodes, splits);
+    let part2 = largest_sum(&numbers);
     println!("Part2: {}", part2);
 }
 
-/// Number is an intermediate representation. I can't reason about pairs,
-/// but I can do the adding, exploding, and spliting with this IR.
-#[derive(Debug, Clone)]
-struct Number(Vec<Atom>);
-#[derive(Debug, Clone, Copy)]
-enum Atom {
-    Open,
-    Close,
-    Num(u64),
+/// The largest magnitude of the sum of any two different numbers. Each
+/// thread takes a share of the ordered pairs.
+fn largest_sum(numbers: &[Number]) -> u64 {
+    let pairs: Vec<(usize, usize)> = (0..numbers.len())
+        .flat_map(|i| (0..numbers.len()).map(move |j| (i, j)))
+        .filter(|(i, j)| i != j)
+        .collect();
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk = pairs.len().div_ceil(threads).max(1);
+    thread::scope(|s| {
+        let handles: Vec<_> = pairs
+            .chunks(chunk)
+            .map(|pairs| {
+                s.spawn(move || {
+                    pairs
+                        .iter()
+                        .map(|&(i, j)| {
+                            (numbers[i].clone() + numbers[j].clone())
+                                .magnitude()
+                        })
+                        .max()
+                        .unwrap_or(0)
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|h| h.join().unwrap())
+            .max()
+            .unwrap_or(0)
+    })
 }
 
-/// Pair represents the nested structure of the actual number.
-#[derive(Debug, Default, Clone)]
-struct Pair {
-    store: Vec<Item>,
-    pair: (usize, usize),
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Number {
+    Regular(u64),
+    Pair(Box<Number>, Box<Number>),
 }
-#[derive(Debug, Clone, Copy)]
-enum Item {
-    Num(u64),
-    Pair(usize, usize),
+
+/// A single step of reduction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    /// The pair `[lhs,rhs]` was nested inside four pairs and exploded.
+    Explode(u64, u64),
+    /// The regular number was 10 or greater and was split.
+    Split(u64),
 }
 
-impl<'a> Pair {
-    fn parse_item(&mut self, atoms: &'a [Atom]) -> (Item, &'a [Atom]) {
-        match &atoms[0] {
-            Atom::Num(n) => (Item::Num(*n), &atoms[1..]),
-            Atom::Open => {
-                let (first, atoms) = self.parse_item(&atoms[1..]);
-                let (second, atoms) = self.parse_item(atoms);
-                (
-                    Item::Pair(self.store(first), self.store(second)),
-                    &atoms[1..],
-                )
-            }
-            _ => unreachable!(),
+/// Iterator over the steps taken to reduce a number, yielding each action
+/// along with the number it left behind.
+struct Trace(Number);
+
+/// A pair that has exploded, along with the halves that haven't yet been
+/// added to a neighbouring regular number.
+struct Exploded {
+    pair: (u64, u64),
+    carry_l: Option<u64>,
+    carry_r: Option<u64>,
+}
+
+impl Iterator for Trace {
+    type Item = (Action, Number);
+    fn next(&mut self) -> Option<Self::Item> {
+        let action = self.0.reduce_once()?;
+        Some((action, self.0.clone()))
+    }
+}
+
+impl Number {
+    fn pair(lhs: Number, rhs: Number) -> Number {
+        Number::Pair(Box::new(lhs), Box::new(rhs))
+    }
+
+    fn magnitude(&self) -> u64 {
+        match self {
+            Number::Regular(n) => *n,
+            Number::Pair(lhs, rhs) => 3 * lhs.magnitude() + 2 * rhs.magnitude(),
         }
     }
 
-    fn store(&mut self, i: Item) -> usize {
-        let id = self.store.len();
-        self.store.push(i);
-        id
+    fn reduce(&mut self) {
+        while self.reduce_once().is_some() {}
     }
 
-    fn item(&self, i: usize) -> &Item {
-        &self.store[i]
+    fn trace(self) -> Trace {
+        Trace(self)
     }
 
-    fn magnitude(&self) -> u64 {
-        self.magnitude_of(&Item::Pair(self.pair.0, self.pair.1))
+    /// Applies the next reduction action, if there is one. Explosions always
+    /// take priority over splits.
+    fn reduce_once(&mut self) -> Option<Action> {
+        self.explode().or_else(|| self.split())
     }
 
-    fn magnitude_of(&self, i: &Item) -> u64 {
-        match i {
-            Item::Num(n) => *n,
-            Item::Pair(first, second) => {
-                (self.magnitude_of(self.item(*first)) * 3)
-                    + (self.magnitude_of(self.item(*second)) * 2)
-            }
-        }
+    fn explode(&mut self) -> Option<Action> {
+        self.explode_carry(0).map(
+            |Exploded {
+                 pair: (lhs, rhs), ..
+             }| Action::Explode(lhs, rhs),
+        )
     }
-}
 
-impl From<Number> for Pair {
-    fn from(Number(n): Number) -> Self {
-        let mut pair = Pair::default();
-        let (Item::Pair(first, second), atoms) = pair.parse_item(&n) else {
-            panic!("expected a pair!!");
+    /// Explodes the leftmost pair nested inside four pairs. The parts of the
+    /// exploded pair that still need adding to their neighbours are carried
+    /// back up the tree until a neighbour is found.
+    fn explode_carry(&mut self, depth: usize) -> Option<Exploded> {
+        let Number::Pair(lhs, rhs) = self else {
+            return None;
         };
-        assert!(atoms.is_empty());
-        pair.pair = (first, second);
-        pair
+        if depth >= 4 {
+            if let (Number::Regular(l), Number::Regular(r)) = (&**lhs, &**rhs) {
+                let (l, r) = (*l, *r);
+                *self = Number::Regular(0);
+                return Some(Exploded {
+                    pair: (l, r),
+                    carry_l: Some(l),
+                    carry_r: Some(r),
+                });
+            }
+        }
+        if let Some(mut e) = lhs.explode_carry(depth + 1) {
+            e.carry_r = e.carry_r.and_then(|r| rhs.add_leftmost(r));
+            return Some(e);
+        }
+        if let Some(mut e) = rhs.explode_carry(depth + 1) {
+            e.carry_l = e.carry_l.and_then(|l| lhs.add_rightmost(l));
+            return Some(e);
+        }
+        None
     }
-}
 
-impl Number {
-    fn reduce(&mut self) {
-        loop {
-            while self.explode_once() {}
-            if !self.split_once() {
-                break;
+    /// Adds `n` to the leftmost regular number, returning `None` once it has
+    /// been absorbed.
+    fn add_leftmost(&mut self, n: u64) -> Option<u64> {
+        match self {
+            Number::Regular(m) => {
+                *m += n;
+                None
             }
+            Number::Pair(lhs, _) => lhs.add_leftmost(n),
         }
     }
-    fn explode_once(&mut self) -> bool {
-        let mut stack = Vec::new();
-        let mut prev: Option<usize> = None;
-        let mut open_count = 0;
-        let mut items = self.0.iter();
-        while let Some(i) = items.next() {
-            match i {
-                Atom::Open => {
-                    open_count += 1;
-                    if open_count > 4 {
-                        // Consume the 'n,m]' of this pair...
-                        let Atom::Num(lhs) = items.next().unwrap() else {
-                            panic!("expected a num!");
-                        };
-                        let Atom::Num(rhs) = items.next().unwrap() else {
-                            panic!("expected a num!");
-                        };
-                        items.next().unwrap();
-
-                        // Add lhs to the previous num.
-                        if let Some(idx) = prev.take() {
-                            let Atom::Num(n) = &mut stack[idx] else {
-                                panic!("expected a num!");
-                            };
-                            *n += lhs;
-                        }
-
-                        // Replace this pair with zero.
-                        stack.push(Atom::Num(0));
-                        let mut explode_rhs = Some(rhs);
-
-                        // And now the rest of the list, incrementing the next number we find.
-                        for i in items.by_ref() {
-                            match i {
-                                Atom::Num(n) if explode_rhs.is_some() => stack
-                                    .push(Atom::Num(
-                                        n + explode_rhs.take().unwrap(),
-                                    )),
-                                _ => stack.push(*i),
-                            }
-                        }
-                        self.0 = stack;
-                        return true;
-                    }
-                    stack.push(Atom::Open);
-                }
-                Atom::Close => {
-                    open_count -= 1;
-                    stack.push(Atom::Close);
-                }
-                Atom::Num(n) => {
-                    prev = Some(stack.len());
-                    stack.push(Atom::Num(*n));
-                }
+
+    fn add_rightmost(&mut self, n: u64) -> Option<u64> {
+        match self {
+            Number::Regular(m) => {
+                *m += n;
+                None
             }
+            Number::Pair(_, rhs) => rhs.add_rightmost(n),
         }
-        false
     }
 
-    fn split_once(&mut self) -> bool {
-        let mut stack = Vec::new();
-        let mut items = self.0.iter();
-        while let Some(i) = items.next() {
-            match i {
-                Atom::Num(n) if n > &9 => {
-                    let (lhs, rhs) = (n / 2, (n + 1) / 2);
-                    stack.extend_from_slice(&[
-                        Atom::Open,
-                        Atom::Num(lhs),
-                        Atom::Num(rhs),
-                        Atom::Close,
-                    ]);
-                    for i in items.by_ref() {
-                        stack.push(*i);
-                    }
-                    self.0 = stack;
-                    return true;
-                }
-                _ => {
-                    stack.push(*i);
-                }
+    /// Splits the leftmost regular number that is 10 or greater.
+    fn split(&mut self) -> Option<Action> {
+        match self {
+            Number::Regular(n) if *n > 9 => {
+                let n = *n;
+                *self = Number::pair(
+                    Number::Regular(n / 2),
+                    Number::Regular(n.div_ceil(2)),
+                );
+                Some(Action::Split(n))
             }
+            Number::Regular(_) => None,
+            Number::Pair(lhs, rhs) => lhs.split().or_else(|| rhs.split()),
         }
-        false
     }
 }
 
 impl Add for Number {
     type Output = Number;
     fn add(self, rhs: Self) -> Self::Output {
-        let mut output = vec![Atom::Open];
-        output.extend(self.0.into_iter());
-        output.extend(rhs.0.into_iter());
-        output.push(Atom::Close);
-        let mut n = Number(output);
+        let mut n = Number::pair(self, rhs);
         n.reduce();
         n
     }
 }
 
 impl FromStr for Number {
-    type Err = ();
+    type Err = anyhow::Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut items = Vec::new();
-        let mut accum = Vec::new();
-        for c in s.trim().chars() {
-            match c {
-                '[' => items.push(Atom::Open),
-                ',' => {
-                    if !accum.is_empty() {
-                        let n = accum
-                            .iter()
-                            .collect::<String>()
-                            .parse::<u64>()
-                            .unwrap();
-                        accum.clear();
-                        items.push(Atom::Num(n));
-                    }
-                }
-                ']' => {
-                    if !accum.is_empty() {
-                        let n = accum
-                            .iter()
-                            .collect::<String>()
-                            .parse::<u64>()
-                            .unwrap();
-                        accum.clear();
-                        items.push(Atom::Num(n));
-                    }
-                    items.push(Atom::Close);
-                }
-                _ => {
-                    accum.push(c);
-                }
-            }
+        let s = s.trim();
+        let (n, rest) = parse_number(s)?;
+        if !rest.is_empty() {
+            bail!("trailing input {:?} in {:?}", rest, s);
         }
-        Ok(Number(items))
+        Ok(n)
+    }
+}
+
+/// Parses a number off the front of `s`, returning the rest of the input.
+fn parse_number(s: &str) -> anyhow::Result<(Number, &str)> {
+    if let Some(s) = s.strip_prefix('[') {
+        let (lhs, s) = parse_number(s)?;
+        let s = s
+            .strip_prefix(',')
+            .ok_or_else(|| anyhow!("expected ',' at {:?}", s))?;
+        let (rhs, s) = parse_number(s)?;
+        let s = s
+            .strip_prefix(']')
+            .ok_or_else(|| anyhow!("expected ']' at {:?}", s))?;
+        return Ok((Number::pair(lhs, rhs), s));
     }
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    if end == 0 {
+        bail!("expected '[' or a digit at {:?}", s);
+    }
+    let (digits, s) = s.split_at(end);
+    Ok((Number::Regular(digits.parse()?), s))
 }
 
 impl Display for Number {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        use Atom::*;
-        let mut prev = Atom::Open;
-        for i in &self.0 {
-            match (i, prev) {
-                (&Open, Open) => write!(f, "[")?,
-                (Open, _) => write!(f, ",[")?,
-                (&Close, _) => write!(f, "]")?,
-                (&Num(n), Open) => write!(f, "{}", n)?,
-                (Num(n), _) => write!(f, ",{}", n)?,
-            }
-            prev = *i;
+        match self {
+            Number::Regular(n) => write!(f, "{}", n),
+            Number::Pair(lhs, rhs) => write!(f, "[{},{}]", lhs, rhs),
+        }
+    }
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Action::Explode(lhs, rhs) => write!(f, "explode [{},{}]", lhs, rhs),
+            Action::Split(n) => write!(f, "split {}", n),
         }
-        Ok(())
     }
 }
 
@@ -300,31 +287,68 @@ mod tests {
     #[test]
     fn test_explode() {
         let mut n = Number::from_str("[[[[[9,8],1],2],3],4]").unwrap();
-        n.explode_once();
+        assert_eq!(n.explode(), Some(Action::Explode(9, 8)));
         assert_eq!(format!("{}", n), "[[[[0,9],2],3],4]");
 
         let mut n = Number::from_str("[7,[6,[5,[4,[3,2]]]]]").unwrap();
-        n.explode_once();
+        n.explode();
         assert_eq!(format!("{}", n), "[7,[6,[5,[7,0]]]]");
 
         let mut n =
             Number::from_str("[[3,[2,[1,[7,3]]]],[6,[5,[4,[3,2]]]]]").unwrap();
-        n.explode_once();
+        n.explode();
         assert_eq!(format!("{}", n), "[[3,[2,[8,0]]],[9,[5,[4,[3,2]]]]]");
 
         let mut n =
             Number::from_str("[[3,[2,[8,0]]],[9,[5,[4,[3,2]]]]]").unwrap();
-        n.explode_once();
+        n.explode();
         assert_eq!(format!("{}", n), "[[3,[2,[8,0]]],[9,[5,[7,0]]]]");
     }
 
+    #[test]
+    fn test_trace() {
+        let n = Number::pair(
+            "[[[[4,3],4],4],[7,[[8,4],9]]]".parse().unwrap(),
+            "[1,1]".parse().unwrap(),
+        );
+        let trace: Vec<_> = n
+            .trace()
+            .map(|(action, n)| format!("{}: {}", action, n))
+            .collect();
+        assert_eq!(
+            trace,
+            vec![
+                "explode [4,3]: [[[[0,7],4],[7,[[8,4],9]]],[1,1]]",
+                "explode [8,4]: [[[[0,7],4],[15,[0,13]]],[1,1]]",
+                "split 15: [[[[0,7],4],[[7,8],[0,13]]],[1,1]]",
+                "split 13: [[[[0,7],4],[[7,8],[0,[6,7]]]],[1,1]]",
+                "explode [6,7]: [[[[0,7],4],[[7,8],[6,0]]],[8,1]]",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for s in input::string("input/day18_test.txt").lines() {
+            assert_eq!(s.parse::<Number>().unwrap().to_string(), s);
+        }
+        assert!("[1,2".parse::<Number>().is_err());
+        assert!("[1;2]".parse::<Number>().is_err());
+        assert!("[1,2]]".parse::<Number>().is_err());
+    }
+
     #[test]
     fn test_magnitude() {
         let n = Number::from_str(
             "[[[[8,7],[7,7]],[[8,6],[7,7]]],[[[0,7],[6,6]],[8,7]]]",
         )
         .unwrap();
-        let p = Pair::from(n);
-        assert_eq!(p.magnitude(), 3488);
+        assert_eq!(n.magnitude(), 3488);
+    }
+
+    #[test]
+    fn test_largest_sum() {
+        let numbers: Vec<Number> = input::parse_lines("input/day18_test.txt");
+        assert_eq!(largest_sum(&numbers), 3993);
     }
 }
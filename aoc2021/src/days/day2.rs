@@ -1,74 +1,176 @@
-use std::str::FromStr;
-
-use anyhow::{anyhow, bail};
+use anyhow::bail;
 use input::parse_lines;
+use util::{
+    dir::{Command, Dir},
+    pos::Pos,
+};
 
 const INPUT: &str = "input/day2.txt";
 
 pub fn solution() {
-    let data: Vec<Instruction> = parse_lines(INPUT);
-    {
-        let mut horiz = 0;
-        let mut depth = 0;
-        for inst in &data {
-            match inst.direction {
-                Dir::Up => depth -= inst.magnitute,
-                Dir::Down => depth += inst.magnitute,
-                Dir::Forward => horiz += inst.magnitute,
-            }
+    let commands: Vec<Command> = parse_lines(INPUT);
+    let models = Models::default();
+    for (part, model) in ["plain", "aim"].into_iter().enumerate() {
+        let mut sub = models.create(model).unwrap();
+        let positions = trace(sub.as_mut(), &commands)
+            .collect::<anyhow::Result<Vec<Pos>>>()
+            .unwrap();
+        let Pos(horiz, depth) = positions.last().copied().unwrap_or_default();
+        println!("part{}: {}", part + 1, horiz * depth);
+    }
+}
+
+/// A way of interpreting commands. Positions are `Pos(horizontal, depth)`.
+trait Submarine {
+    fn execute(&mut self, command: &Command) -> anyhow::Result<()>;
+    fn position(&self) -> Pos;
+}
+
+/// Up and down change depth directly.
+#[derive(Debug, Default)]
+struct Plain {
+    pos: Pos,
+}
+
+impl Submarine for Plain {
+    fn execute(&mut self, &Command { dir, n }: &Command) -> anyhow::Result<()> {
+        match dir {
+            Dir::Up => self.pos.1 -= n,
+            Dir::Down => self.pos.1 += n,
+            Dir::Forward => self.pos.0 += n,
+            _ => bail!("submarines can't go {:?}", dir),
         }
-        let part1 = horiz * depth;
-        println!("part1: {}", part1);
+        Ok(())
     }
-    {
-        let mut horiz = 0;
-        let mut depth = 0;
-        let mut aim = 0;
-        for inst in data {
-            match inst.direction {
-                Dir::Up => aim -= inst.magnitute,
-                Dir::Down => aim += inst.magnitute,
-                Dir::Forward => {
-                    horiz += inst.magnitute;
-                    depth += aim * inst.magnitute;
-                }
-            }
+
+    fn position(&self) -> Pos {
+        self.pos
+    }
+}
+
+/// Up and down change the aim, and moving forward dives along it.
+#[derive(Debug, Default)]
+struct Aimed {
+    pos: Pos,
+    aim: i64,
+}
+
+impl Submarine for Aimed {
+    fn execute(&mut self, &Command { dir, n }: &Command) -> anyhow::Result<()> {
+        match dir {
+            Dir::Up => self.aim -= n,
+            Dir::Down => self.aim += n,
+            Dir::Forward => self.pos += Pos(n, self.aim * n),
+            _ => bail!("submarines can't go {:?}", dir),
         }
-        let part1 = horiz * depth;
-        println!("part2: {}", part1);
+        Ok(())
+    }
+
+    fn position(&self) -> Pos {
+        self.pos
+    }
+}
+
+/// Builds a fresh submarine of some model.
+type Constructor = fn() -> Box<dyn Submarine>;
+
+/// The movement models we know about, by name.
+struct Models(Vec<(&'static str, Constructor)>);
+
+impl Default for Models {
+    fn default() -> Self {
+        let mut models = Models(Vec::new());
+        models.register("plain", || Box::<Plain>::default());
+        models.register("aim", || Box::<Aimed>::default());
+        models
     }
 }
 
-#[derive(Debug)]
-enum Dir {
-    Up,
-    Down,
-    Forward,
+impl Models {
+    /// Adds a model, replacing any existing one with the same name.
+    fn register(&mut self, name: &'static str, new: Constructor) {
+        self.0.retain(|(n, _)| *n != name);
+        self.0.push((name, new));
+    }
+
+    fn create(&self, name: &str) -> Option<Box<dyn Submarine>> {
+        self.0
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, new)| new())
+    }
 }
 
-#[derive(Debug)]
-struct Instruction {
-    direction: Dir,
-    magnitute: i64,
+/// Runs each command in turn, yielding the position after each. A command
+/// the submarine rejects yields an error and leaves it where it was, so
+/// collect into a `Result` rather than looking only at the last item.
+fn trace<'a>(
+    sub: &'a mut dyn Submarine,
+    commands: &'a [Command],
+) -> impl Iterator<Item = anyhow::Result<Pos>> + 'a {
+    commands
+        .iter()
+        .map(move |c| sub.execute(c).map(|_| sub.position()))
 }
 
-impl FromStr for Instruction {
-    type Err = anyhow::Error;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (dir, mag) = s
-            .split_once(' ')
-            .ok_or_else(|| anyhow!("couldn't split on ' '"))?;
-        let mag = i64::from_str(mag)?;
-        let dir = match dir {
-            "forward" => Dir::Forward,
-            "up" => Dir::Up,
-            "down" => Dir::Down,
-            _ => bail!("invalid direction"),
-        };
-        Ok(Instruction {
-            direction: dir,
-            magnitute: mag,
-        })
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> Vec<Command> {
+        input::parse_str_lines(
+            "forward 5\ndown 5\nforward 8\nup 3\ndown 8\nforward 2",
+        )
+    }
+
+    #[test]
+    fn test_models() {
+        let models = Models::default();
+        let mut plain = models.create("plain").unwrap();
+        let positions: Vec<_> = trace(plain.as_mut(), &example())
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(positions[0], Pos(5, 0));
+        assert_eq!(positions.last(), Some(&Pos(15, 10)));
+
+        let mut aimed = models.create("aim").unwrap();
+        let end = trace(aimed.as_mut(), &example()).last().unwrap();
+        assert_eq!(end.unwrap(), Pos(15, 60));
+    }
+
+    #[test]
+    fn test_register() {
+        /// Moves twice as far as it's told.
+        #[derive(Default)]
+        struct Eager(Plain);
+        impl Submarine for Eager {
+            fn execute(&mut self, c: &Command) -> anyhow::Result<()> {
+                self.0.execute(&Command { n: c.n * 2, ..*c })
+            }
+            fn position(&self) -> Pos {
+                self.0.position()
+            }
+        }
+
+        let mut models = Models::default();
+        assert!(models.create("eager").is_none());
+        models.register("eager", || Box::<Eager>::default());
+        let mut eager = models.create("eager").unwrap();
+        let end = trace(eager.as_mut(), &example()).last().unwrap();
+        assert_eq!(end.unwrap(), Pos(30, 20));
+
+        let left = [Command {
+            dir: Dir::Left,
+            n: 1,
+        }];
+        assert!(trace(eager.as_mut(), &left).next().unwrap().is_err());
+
+        // A bad command in the middle spoils the whole trace.
+        let mut commands = example();
+        commands.insert(2, left[0]);
+        let mut plain = models.create("plain").unwrap();
+        let positions: anyhow::Result<Vec<Pos>> =
+            trace(plain.as_mut(), &commands).collect();
+        assert!(positions.is_err());
     }
 }
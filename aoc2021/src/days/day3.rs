@@ -1,101 +1,181 @@
-use std::str::FromStr;
+use std::{cmp::Ordering, fmt::Display, ops::Range, str::FromStr};
 
+use anyhow::{anyhow, bail};
 use input::{input_path, parse_lines};
 
 const INPUT: &str = "input/day3.txt";
 
 pub fn solution() {
-    let data: Vec<Num> = parse_lines(input_path(INPUT));
-    let len = data[0].len;
-
-    {
-        let data: Vec<u32> = data.iter().map(|n| n.n).collect();
-
-        let mut gamma = 0;
-        let mut epsilon = 0;
-        for i in 0..len {
-            let shift = len - i - 1;
-            let num_ones = data
-                .iter()
-                .flat_map(|n| {
-                    let n = n >> shift;
-                    if n & 1 == 1 {
-                        Some(())
-                    } else {
-                        None
-                    }
-                })
-                .count();
-            let num_zeros = data.len() - num_ones;
-            if num_ones > num_zeros {
-                gamma |= 1 << shift;
-            } else {
-                epsilon |= 1 << shift;
-            }
-        }
-        println!("part1: {}", gamma * epsilon);
-    }
-
-    let o = oxygen(data.clone());
-    let c = co2(data);
-    println!("part2: {}", o * c);
+    let report = BitMatrix::new(parse_lines(input_path(INPUT))).unwrap();
+
+    // Ties never come up in the real input, but the puzzle would leave them
+    // out of gamma.
+    let gamma = report.most_common(Bit::Zero);
+    let epsilon = report.least_common(Bit::One);
+    println!("part1: {}", gamma.value() * epsilon.value());
+
+    let o = report.rating(Criterion::MostCommon(Bit::One));
+    let c = report.rating(Criterion::LeastCommon(Bit::Zero));
+    println!("part2: {}", o.word.value() * c.word.value());
+    println!("oxygen candidates: {}", o.narrowing());
+    println!("co2 candidates: {}", c.narrowing());
 }
 
-fn oxygen(nums: Vec<Num>) -> u32 {
-    rating(nums, |zeros, ones| ones >= zeros)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bit {
+    Zero,
+    One,
 }
 
-fn co2(nums: Vec<Num>) -> u32 {
-    rating(nums, |zeros, ones| zeros > ones)
+/// Which bit to keep when filtering for a rating. Each variant holds the bit
+/// to keep when zeros and ones are equally common.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Criterion {
+    MostCommon(Bit),
+    LeastCommon(Bit),
 }
 
-fn rating(mut nums: Vec<Num>, keep: impl Fn(usize, usize) -> bool) -> u32 {
-    let len = nums[0].len;
-    for i in 0..len {
-        if nums.len() == 1 {
-            return nums[0].n;
-        }
-        let (zeros, ones) = count_nth(i, &nums);
-        let bit = keep(zeros, ones);
-        nums = filter(i, bit, &nums);
+/// A binary number of any width, most significant bit first.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct Word(Vec<bool>);
+
+impl Word {
+    /// The word as a number. Panics if it's wider than 128 bits.
+    fn value(&self) -> u128 {
+        assert!(self.0.len() <= 128, "{} bits is too wide", self.0.len());
+        self.0.iter().fold(0, |n, b| n << 1 | *b as u128)
     }
-    assert!(nums.len() == 1);
-    nums[0].n
 }
 
-fn filter(i: usize, bit: bool, nums: &[Num]) -> Vec<Num> {
-    nums.iter().filter(|n| n.nth(i) == bit).cloned().collect()
+impl FromStr for Word {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.trim()
+            .chars()
+            .map(|c| match c {
+                '0' => Ok(false),
+                '1' => Ok(true),
+                _ => Err(anyhow!("{:?} isn't a bit", c)),
+            })
+            .collect::<Result<_, _>>()
+            .map(Word)
+    }
 }
 
-fn count_nth(i: usize, nums: &[Num]) -> (usize, usize) {
-    let mut ones = 0;
-    for n in nums {
-        if n.nth(i) {
-            ones += 1;
+impl Display for Word {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for b in &self.0 {
+            write!(f, "{}", *b as u8)?;
         }
+        Ok(())
     }
-    (nums.len() - ones, ones)
 }
 
+/// A set of equal-width words, kept sorted so that every group of words
+/// sharing a prefix is a contiguous run.
 #[derive(Debug, Clone)]
-struct Num {
-    n: u32,
-    len: usize,
+struct BitMatrix {
+    width: usize,
+    rows: Vec<Word>,
 }
-impl FromStr for Num {
-    type Err = anyhow::Error;
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let n = u32::from_str_radix(s, 2)?;
-        Ok(Num { n, len: s.len() })
+/// A rating found by filtering, along with the candidates that were left
+/// before each bit was considered.
+#[derive(Debug)]
+struct Rating<'a> {
+    word: &'a Word,
+    candidates: Vec<&'a [Word]>,
+}
+
+impl BitMatrix {
+    fn new(mut rows: Vec<Word>) -> anyhow::Result<Self> {
+        let Some(width) = rows.first().map(|w| w.0.len()) else {
+            bail!("no words");
+        };
+        if let Some(w) = rows.iter().find(|w| w.0.len() != width) {
+            bail!("{} isn't {} bits wide", w, width);
+        }
+        rows.sort();
+        Ok(BitMatrix { width, rows })
+    }
+
+    /// The number of ones in each column.
+    fn popcounts(&self) -> Vec<usize> {
+        (0..self.width)
+            .map(|col| self.rows.iter().filter(|w| w.0[col]).count())
+            .collect()
+    }
+
+    /// Each column's most common bit, or `tie` if there isn't one.
+    fn most_common(&self, tie: Bit) -> Word {
+        self.mask(Criterion::MostCommon(tie))
+    }
+
+    /// Each column's least common bit, or `tie` if there isn't one.
+    fn least_common(&self, tie: Bit) -> Word {
+        self.mask(Criterion::LeastCommon(tie))
+    }
+
+    fn mask(&self, criterion: Criterion) -> Word {
+        let n = self.rows.len();
+        Word(
+            self.popcounts()
+                .into_iter()
+                .map(|ones| criterion.keep(n - ones, ones) == Bit::One)
+                .collect(),
+        )
+    }
+
+    /// Narrows the words down one bit at a time, keeping those whose bit
+    /// matches `criterion` among the remaining candidates, until only one
+    /// is left. Since the rows are sorted, each step just splits a range.
+    fn rating(&self, criterion: Criterion) -> Rating<'_> {
+        let mut range: Range<usize> = 0..self.rows.len();
+        let mut candidates = Vec::new();
+        for col in 0..self.width {
+            candidates.push(&self.rows[range.clone()]);
+            if range.len() == 1 {
+                break;
+            }
+            let split = range.start
+                + self.rows[range.clone()].partition_point(|w| !w.0[col]);
+            let (zeros, ones) = (split - range.start, range.end - split);
+            range = match criterion.keep(zeros, ones) {
+                // Never filter down to nothing.
+                Bit::One if ones > 0 => split..range.end,
+                Bit::Zero if zeros > 0 => range.start..split,
+                _ => range,
+            };
+        }
+        Rating {
+            word: &self.rows[range.start],
+            candidates,
+        }
     }
 }
 
-impl Num {
-    fn nth(&self, i: usize) -> bool {
-        let shift = self.len - i - 1;
-        let mask = 1 << shift;
-        self.n & mask != 0
+impl Rating<'_> {
+    /// How many candidates were left before each bit, e.g. "12 -> 7 -> 4".
+    fn narrowing(&self) -> String {
+        self.candidates
+            .iter()
+            .map(|c| c.len().to_string())
+            .collect::<Vec<_>>()
+            .join(" -> ")
+    }
+}
+
+impl Criterion {
+    fn keep(&self, zeros: usize, ones: usize) -> Bit {
+        let (most, tie) = match *self {
+            Criterion::MostCommon(tie) => (true, tie),
+            Criterion::LeastCommon(tie) => (false, tie),
+        };
+        match (ones.cmp(&zeros), most) {
+            (Ordering::Equal, _) => tie,
+            (Ordering::Greater, true) | (Ordering::Less, false) => Bit::One,
+            _ => Bit::Zero,
+        }
     }
 }
 
@@ -103,47 +183,71 @@ impl Num {
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_nth() {
-        let n = Num::from_str("100").unwrap();
-        assert_eq!(n.nth(0), true);
-        assert_eq!(n.nth(1), false);
-        assert_eq!(n.nth(2), false);
-
-        let n = Num::from_str("001").unwrap();
-        assert_eq!(n.nth(0), false);
-        assert_eq!(n.nth(1), false);
-        assert_eq!(n.nth(2), true);
-
-        let n = Num::from_str("111").unwrap();
-        assert_eq!(n.nth(0), true);
-        assert_eq!(n.nth(1), true);
-        assert_eq!(n.nth(2), true);
-    }
-
-    #[test]
-    fn test_oxygen() {
-        let nums = [
+    fn example() -> BitMatrix {
+        let rows = [
             "00100", "11110", "10110", "10111", "10101", "01111", "00111",
             "11100", "10000", "11001", "00010", "01010",
         ]
         .into_iter()
-        .map(Num::from_str)
+        .map(Word::from_str)
         .map(Result::unwrap)
         .collect::<Vec<_>>();
-        assert_eq!(oxygen(nums), 23);
+        BitMatrix::new(rows).unwrap()
+    }
+
+    #[test]
+    fn test_word() {
+        let w = Word::from_str("100").unwrap();
+        assert_eq!(w.0, vec![true, false, false]);
+        assert_eq!(w.value(), 4);
+        assert_eq!(w.to_string(), "100");
+        assert!(Word::from_str("102").is_err());
+    }
+
+    #[test]
+    fn test_power() {
+        let m = example();
+        assert_eq!(m.popcounts(), vec![7, 5, 8, 7, 5]);
+        assert_eq!(m.most_common(Bit::Zero).value(), 22);
+        assert_eq!(m.least_common(Bit::One).value(), 9);
+    }
+
+    #[test]
+    fn test_oxygen() {
+        let m = example();
+        let r = m.rating(Criterion::MostCommon(Bit::One));
+        assert_eq!(r.word.value(), 23);
+        let sizes: Vec<_> = r.candidates.iter().map(|c| c.len()).collect();
+        assert_eq!(sizes, vec![12, 7, 4, 3, 2]);
+        assert_eq!(r.narrowing(), "12 -> 7 -> 4 -> 3 -> 2");
     }
 
     #[test]
     fn test_co2() {
-        let nums = [
-            "00100", "11110", "10110", "10111", "10101", "01111", "00111",
-            "11100", "10000", "11001", "00010", "01010",
-        ]
-        .into_iter()
-        .map(Num::from_str)
-        .map(Result::unwrap)
-        .collect::<Vec<_>>();
-        assert_eq!(co2(nums), 10);
+        let m = example();
+        let r = m.rating(Criterion::LeastCommon(Bit::Zero));
+        assert_eq!(r.word.value(), 10);
+        assert_eq!(r.candidates.last().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_wide() {
+        let one = format!("1{}", "0".repeat(99));
+        let zero = "0".repeat(100);
+        let m = BitMatrix::new(vec![
+            one.parse().unwrap(),
+            one.parse().unwrap(),
+            zero.parse().unwrap(),
+        ])
+        .unwrap();
+        assert_eq!(m.most_common(Bit::Zero).to_string(), one);
+        assert_eq!(
+            m.rating(Criterion::LeastCommon(Bit::Zero)).word.to_string(),
+            zero
+        );
+        assert!(
+            BitMatrix::new(vec!["1".parse().unwrap(), m.rows[0].clone()])
+                .is_err()
+        );
     }
 }
@@ -1,107 +1,255 @@
-use std::{collections::HashSet, str::FromStr};
+use std::{
+    collections::{HashMap, VecDeque},
+    str::FromStr,
+};
+
+use anyhow::{anyhow, bail, Context};
+use input::{CsvVec, SpaceVec};
 
 const INPUT: &str = "input/day4.txt";
 
 pub fn solution() {
-    let mut bingo: Bingo = input::parse(input::path(INPUT));
-
-    let mut won = HashSet::new();
-    let mut first = 0;
-    let mut last = 0;
-    for n in bingo.rand {
-        for (i, b) in bingo.boards.iter_mut().enumerate() {
-            if won.contains(&i) {
-                continue;
-            }
-            if let Some(win) = b.mark(n) {
-                if first == 0 {
-                    first = win;
-                }
-                last = win;
-                won.insert(i);
-            }
-        }
-    }
+    let bingo: Bingo = input::parse(input::path(INPUT));
+    let wins: Vec<Win> = bingo.play(Rules::default()).collect();
+    let first = wins.first().unwrap().score;
+    let last = wins.last().unwrap().score;
     println!("Part1: {}\nPart2: {}", first, last);
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Bingo {
-    rand: Vec<u8>,
+    draws: Vec<u32>,
     boards: Vec<Board>,
 }
 
-#[derive(Default, Debug)]
+#[derive(Debug, Clone, Copy, Default)]
+struct Rules {
+    /// Whether a fully marked diagonal counts as a win.
+    diagonals: bool,
+}
+
+/// A board completing a line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Win {
+    board: usize,
+    draw: u32,
+    draw_index: usize,
+    score: u64,
+}
+
+#[derive(Debug, Clone)]
 struct Board {
-    board: Vec<Vec<u8>>,
-    marks: Vec<(usize, usize)>,
+    size: usize,
+    /// Where each number sits on the board, as `(row, col)`.
+    cells: HashMap<u32, (usize, usize)>,
+    /// The number of marks in each row, column and diagonal.
+    rows: Vec<usize>,
+    cols: Vec<usize>,
+    diags: [usize; 2],
+    unmarked_sum: u64,
 }
 
 impl Board {
-    fn mark(&mut self, n: u8) -> Option<u64> {
-        for x in 0..5 {
-            for y in 0..5 {
-                if self.board[x][y] == n {
-                    self.marks.push((x, y));
-                    if self.has_bingo() {
-                        return Some(n as u64 * self.unmarked_sum());
-                    }
-                }
-            }
+    /// Marks `n`, returning whether that completed a line.
+    fn mark(&mut self, n: u32, rules: Rules) -> bool {
+        let Some((r, c)) = self.cells.remove(&n) else {
+            return false;
+        };
+        self.unmarked_sum -= n as u64;
+        self.rows[r] += 1;
+        self.cols[c] += 1;
+        if r == c {
+            self.diags[0] += 1;
+        }
+        if r + c == self.size - 1 {
+            self.diags[1] += 1;
         }
-        None
+        self.rows[r] == self.size
+            || self.cols[c] == self.size
+            || (rules.diagonals && self.diags.contains(&self.size))
     }
+}
 
-    fn has_bingo(&self) -> bool {
-        for i in 0..5 {
-            // Count the number of marks in the row.
-            let row = self.marks.iter().filter(|row| row.0 == i).count();
-            if row == 5 {
-                return true;
-            }
-            // Count the number of marks in the col.
-            let col = self.marks.iter().filter(|row| row.1 == i).count();
-            if col == 5 {
-                return true;
+impl Bingo {
+    fn play(self, rules: Rules) -> BingoGame {
+        BingoGame {
+            draws: self.draws.into_iter().enumerate(),
+            boards: self.boards.into_iter().map(Some).collect(),
+            rules,
+            wins: VecDeque::new(),
+        }
+    }
+}
+
+/// Iterator over the wins in a game of bingo, in the order they happen.
+/// Boards stop playing once they've won.
+struct BingoGame {
+    draws: std::iter::Enumerate<std::vec::IntoIter<u32>>,
+    boards: Vec<Option<Board>>,
+    rules: Rules,
+    wins: VecDeque<Win>,
+}
+
+impl Iterator for BingoGame {
+    type Item = Win;
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.wins.is_empty() {
+            let (draw_index, draw) = self.draws.next()?;
+            for (i, slot) in self.boards.iter_mut().enumerate() {
+                let Some(board) = slot else {
+                    continue;
+                };
+                if board.mark(draw, self.rules) {
+                    self.wins.push_back(Win {
+                        board: i,
+                        draw,
+                        draw_index,
+                        score: draw as u64 * board.unmarked_sum,
+                    });
+                    *slot = None;
+                }
             }
         }
-        false
+        self.wins.pop_front()
     }
+}
 
-    fn unmarked_sum(&self) -> u64 {
-        let marked = self.marks.iter().collect::<HashSet<_>>();
-        let mut sum = 0;
-        for x in 0..5 {
-            for y in 0..5 {
-                if !marked.contains(&(x, y)) {
-                    sum += self.board[x][y] as u64;
+impl FromStr for Board {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rows = s
+            .lines()
+            .enumerate()
+            .map(|(r, l)| {
+                let SpaceVec(row) =
+                    l.parse().with_context(|| format!("row {}", r))?;
+                Ok(row)
+            })
+            .collect::<anyhow::Result<Vec<Vec<u32>>>>()?;
+        let size = rows.len();
+        let mut cells = HashMap::new();
+        for (r, row) in rows.iter().enumerate() {
+            if row.len() != size {
+                bail!("row {} has {} numbers, expected {}", r, row.len(), size);
+            }
+            for (c, n) in row.iter().enumerate() {
+                if cells.insert(*n, (r, c)).is_some() {
+                    bail!("{} appears twice", n);
                 }
             }
         }
-        sum
+        Ok(Board {
+            size,
+            unmarked_sum: cells.keys().map(|n| *n as u64).sum(),
+            cells,
+            rows: vec![0; size],
+            cols: vec![0; size],
+            diags: [0; 2],
+        })
     }
 }
 
 impl FromStr for Bingo {
     type Err = anyhow::Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut lines = s.lines();
+        let records = input::paragraphs(s);
+        let (draws, boards) = records
+            .split_first()
+            .ok_or_else(|| anyhow!("missing draws"))?;
 
         // first line is the list of "random" draws.
-        let csv = lines.next().unwrap();
-        let rand = input::csv_str(csv);
-
-        let mut boards = Vec::new();
-        while lines.next().is_some() {
-            let mut board = Board::default();
-            for _ in 0..5 {
-                let row = lines.next().unwrap();
-                let row = input::space_str(row);
-                board.board.push(row);
+        let CsvVec(draws) = draws.parse().context("draws")?;
+
+        // The draws are record 1, so boards count from 2.
+        let boards = boards
+            .iter()
+            .enumerate()
+            .map(|(i, b)| {
+                b.parse().map_err(|e| anyhow!("record {}: {:?}", i + 2, e))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Bingo { draws, boards })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+7,4,9,5,11,17,23,2,0,14,21,24,10,16,13,6,15,25,12,22,18,20,8,19,3,26,1
+
+22 13 17 11  0
+ 8  2 23  4 24
+21  9 14 16  7
+ 6 10  3 18  5
+ 1 12 20 15 19
+
+ 3 15  0  2 22
+ 9 18 13 17  5
+19  8  7 25 23
+20 11 10 24  4
+14 21 16 12  6
+
+14 21 17 24  4
+10 16 15  9 19
+18  8 23 26 20
+22 11 13  6  5
+ 2  0 12  3  7
+";
+
+    #[test]
+    fn test_example() {
+        let bingo: Bingo = EXAMPLE.parse().unwrap();
+        let wins: Vec<_> = bingo.play(Rules::default()).collect();
+        assert_eq!(wins.len(), 3);
+        assert_eq!(
+            wins[0],
+            Win {
+                board: 2,
+                draw: 24,
+                draw_index: 11,
+                score: 4512
             }
-            boards.push(board);
-        }
+        );
+        assert_eq!(
+            wins[2],
+            Win {
+                board: 1,
+                draw: 13,
+                draw_index: 14,
+                score: 1924
+            }
+        );
+    }
+
+    #[test]
+    fn test_zero_score() {
+        // A winning draw of zero shouldn't be mistaken for "no winner yet".
+        let bingo: Bingo = "2,0,1\n\n1 2\n3 0\n\n5 2\n6 1".parse().unwrap();
+        let wins: Vec<_> =
+            bingo.play(Rules::default()).map(|w| w.score).collect();
+        assert_eq!(wins, vec![0, 11]);
+    }
+
+    #[test]
+    fn test_diagonals() {
+        let bingo: Bingo = "1,5,9\n\n1 2 3\n4 5 6\n7 8 9".parse().unwrap();
+        assert_eq!(bingo.clone().play(Rules::default()).next(), None);
+        let win = bingo.play(Rules { diagonals: true }).next().unwrap();
+        assert_eq!((win.draw_index, win.score), (2, 9 * 30));
+    }
 
-        Ok(Bingo { rand, boards })
+    #[test]
+    fn test_bad_board() {
+        assert!("1\n\n1 2\n3".parse::<Bingo>().is_err());
+        assert!("1\n\n1 2\n2 3".parse::<Bingo>().is_err());
+        let err = "1\n\n1 2\n3 x".parse::<Bingo>().unwrap_err();
+        assert!(err.to_string().starts_with("record 2: "), "{}", err);
+        let err = "1,x\n\n1 2\n3 4".parse::<Bingo>().unwrap_err();
+        assert_eq!(err.to_string(), "draws");
+        let err = "1\n\n1 2\n3 4\n\n1 2\n3".parse::<Bingo>().unwrap_err();
+        assert!(err.to_string().starts_with("record 3: "), "{}", err);
     }
 }
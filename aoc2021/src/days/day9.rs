@@ -1,39 +1,32 @@
-use std::{
-    collections::{HashMap, HashSet},
-    str::FromStr,
+use util::{
+    components::{Components, Connectivity},
+    pos::{DigitGrid, Pos, PosMap},
 };
 
 const INPUT: &str = "input/day9.txt";
 
 pub fn solution() {
-    let map: Cave = input::parse(INPUT);
-    let mut part1 = 0;
-    let mut basins = Vec::new();
-    for &p in map.height.keys() {
-        if let Some(risk) = map.low_point(p) {
-            part1 += risk;
-            basins.push(map.basin_size(p))
-        }
-    }
-    basins.sort();
-    let part2 = basins
-        .into_iter()
-        .rev()
-        .take(3)
-        .reduce(|accum, item| accum * item)
-        .unwrap();
+    let map = Cave::from(input::parse::<DigitGrid<u32>, _>(INPUT));
+    let part1: u64 = map.height.keys().filter_map(|p| map.low_point(*p)).sum();
+    let part2: usize = map.basin_sizes().iter().take(3).product();
     println!("Part1: {}\nPart2: {}", part1, part2);
 }
 
 #[derive(Debug)]
 struct Cave {
-    height: HashMap<Pos, u32>,
+    height: PosMap<u32>,
+}
+
+impl From<DigitGrid<u32>> for Cave {
+    fn from(DigitGrid(height): DigitGrid<u32>) -> Self {
+        Cave { height }
+    }
 }
 
 impl Cave {
     fn low_point(&self, p: Pos) -> Option<u64> {
         let h = self.height.get(&p).unwrap();
-        if p.neighbors()
+        if p.compass_neighbors()
             .iter()
             .filter_map(|p| self.height.get(p))
             .all(|n| h < n)
@@ -44,43 +37,31 @@ impl Cave {
         }
     }
 
-    fn basin_size(&self, p: Pos) -> usize {
-        let mut stack = vec![p];
-        let mut visited = HashSet::new();
-        while let Some(cur) = stack.pop() {
-            visited.insert(cur);
-            cur.neighbors()
-                .into_iter()
-                .filter(|p| match self.height.get(p) {
-                    Some(h) if h < &9 => !visited.contains(p),
-                    _ => false,
-                })
-                .for_each(|p| stack.push(p));
-        }
-        visited.len()
+    /// The sizes of the basins, largest first. Every cell other than a 9
+    /// belongs to exactly one basin.
+    fn basin_sizes(&self) -> Vec<usize> {
+        let basins =
+            Components::label(&self.height, Connectivity::Four, |_, h| *h < 9);
+        basins
+            .largest(basins.len())
+            .into_iter()
+            .map(|id| basins.sizes()[id])
+            .collect()
     }
 }
 
-#[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
-struct Pos(i64, i64);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-impl Pos {
-    fn neighbors(&self) -> [Pos; 4] {
-        let &Pos(x, y) = self;
-        [Pos(x + 1, y), Pos(x - 1, y), Pos(x, y + 1), Pos(x, y - 1)]
-    }
-}
-
-impl FromStr for Cave {
-    type Err = ();
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut height = HashMap::new();
-        for (y, l) in s.lines().enumerate() {
-            for (x, c) in l.chars().enumerate() {
-                let h = c.to_digit(10).unwrap();
-                height.insert(Pos(x as i64, y as i64), h);
-            }
-        }
-        Ok(Cave { height })
+    #[test]
+    fn test_example() {
+        let map = Cave::from(input::parse::<DigitGrid<u32>, _>(
+            "input/day9_test.txt",
+        ));
+        let part1: u64 =
+            map.height.keys().filter_map(|p| map.low_point(*p)).sum();
+        assert_eq!(part1, 15);
+        assert_eq!(map.basin_sizes(), vec![14, 9, 9, 3]);
     }
 }
@@ -1,44 +1,118 @@
-use std::str::FromStr;
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+};
 
-use input::{input_path, parse_lines};
+use anyhow::anyhow;
+use input::input_path;
+use util::top_k::TopK;
 
 const INPUT: &str = "input/day1.txt";
-
-#[derive(Debug)]
-struct Calories(Option<u32>);
+/// How many elves part 2 adds up, unless the runner says otherwise.
+const K: usize = 3;
 
 pub fn solution() {
-    let cals: Vec<Calories> = parse_lines(input_path(INPUT));
-    let mut elves = count_cals(cals);
-    elves.sort();
-    let elves = elves.into_iter().rev().collect::<Vec<_>>();
-    println!("Part1: {}", elves[0]);
-    println!("Part2: {}", elves[0] + elves[1] + elves[2]);
+    let k = match crate::options().first() {
+        Some(k) => k.parse().expect("K should be a number"),
+        None => K,
+    };
+    let f = BufReader::new(File::open(input_path(INPUT)).unwrap());
+    let top = top_totals(f, k.max(1)).unwrap();
+    println!("Part1: {}", top.first().copied().unwrap_or_default());
+    println!("Part2: {}", top.iter().take(k).sum::<u64>());
+}
+
+/// The `k` largest calorie totals, largest first.
+fn top_totals(reader: impl BufRead, k: usize) -> anyhow::Result<Vec<u64>> {
+    let mut top = TopK::new(k);
+    for total in totals(reader) {
+        top.push(total?);
+    }
+    Ok(top.into_sorted_vec())
 }
 
-fn count_cals(cals: Vec<Calories>) -> Vec<u32> {
-    let mut elves = Vec::new();
-    let mut acc = 0;
-    for Calories(c) in cals {
-        match c {
-            Some(c) => acc += c,
-            None => {
-                elves.push(acc);
-                acc = 0;
+/// Each elf's calorie total, reading one line at a time so that memory use
+/// doesn't depend on the size of the input.
+fn totals<R: BufRead>(reader: R) -> Totals<R> {
+    Totals {
+        reader,
+        line: String::new(),
+        number: 0,
+    }
+}
+
+struct Totals<R> {
+    reader: R,
+    line: String,
+    number: usize,
+}
+
+impl<R: BufRead> Iterator for Totals<R> {
+    type Item = anyhow::Result<u64>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut total = None;
+        loop {
+            self.line.clear();
+            match self.reader.read_line(&mut self.line) {
+                Ok(0) => return total.map(Ok),
+                Ok(_) => self.number += 1,
+                Err(e) => return Some(Err(e.into())),
+            }
+            let line = self.line.trim();
+            if line.is_empty() {
+                // Any number of blank lines ends an elf's list.
+                if total.is_some() {
+                    return total.map(Ok);
+                }
+                continue;
+            }
+            let cals = match line.parse::<u64>() {
+                Ok(cals) => cals,
+                Err(e) => {
+                    let e = anyhow!("line {}: {:?}: {}", self.number, line, e);
+                    return Some(Err(e));
+                }
+            };
+            match total.unwrap_or(0).checked_add(cals) {
+                Some(sum) => total = Some(sum),
+                None => {
+                    let e =
+                        anyhow!("line {}: the total overflows", self.number);
+                    return Some(Err(e));
+                }
             }
         }
     }
-    elves
 }
 
-impl FromStr for Calories {
-    type Err = anyhow::Error;
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s.is_empty() {
-            Ok(Calories(None))
-        } else {
-            let cals = u32::from_str(s)?;
-            Ok(Calories(Some(cals)))
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_last_elf() {
+        // The last elf counts even without a trailing blank line.
+        let totals: Vec<u64> = totals("1000\n2000\n\n\n4000".as_bytes())
+            .collect::<anyhow::Result<_>>()
+            .unwrap();
+        assert_eq!(totals, vec![3000, 4000]);
+    }
+
+    #[test]
+    fn test_top_totals() {
+        let example = "1000\n2000\n3000\n\n4000\n\n5000\n6000\n\n7000\n8000\n\
+                       9000\n\n10000\n";
+        assert_eq!(
+            top_totals(example.as_bytes(), 3).unwrap(),
+            vec![24000, 11000, 10000]
+        );
+        // Fewer elves than asked for is fine.
+        assert_eq!(top_totals("1\n\n2".as_bytes(), 3).unwrap(), vec![2, 1]);
+        assert!(top_totals("".as_bytes(), 3).unwrap().is_empty());
+        let err = top_totals("1\n\n2x".as_bytes(), 3).unwrap_err();
+        assert!(err.to_string().starts_with("line 3: "), "{}", err);
+        let big = format!("1\n\n{}\n1", u64::MAX);
+        let err = top_totals(big.as_bytes(), 3).unwrap_err();
+        assert_eq!(err.to_string(), "line 4: the total overflows");
     }
 }
@@ -58,7 +58,7 @@ impl Iterator for TickIter {
         // Execute the instruction.
         match inst {
             Inst::Noop => (),
-            Inst::Addx(n, counter) if counter == 0 => {
+            Inst::Addx(n, 0) => {
                 self.x += n;
             }
             Inst::Addx(n, counter) => {
@@ -1,148 +1,481 @@
-use std::{collections::VecDeque, str::FromStr};
+use std::{collections::HashMap, collections::VecDeque, str::FromStr};
+
+use anyhow::{anyhow, bail, Context};
 
 const INPUT: &str = "input/day11.txt";
 
 pub fn solution() {
     let mut part1: Monkeys = input::parse(INPUT);
-    let mut part2 = part1.clone();
-    for _ in 0..20 {
-        part1.round(Part::One);
+    let part2 = part1.clone();
+    part1.run(20, Relief::Divide(3)).unwrap();
+    println!("Part1: {}", monkey_business(&part1.inspections()));
+
+    let relief = Relief::Modulo(part2.modulus());
+    let inspections = part2.fast_forward(10000, relief).unwrap();
+    println!("Part2: {}", monkey_business(&inspections));
+}
+
+/// The product of the two largest inspection counts.
+fn monkey_business(inspections: &[usize]) -> usize {
+    let mut inspections = inspections.to_vec();
+    inspections.sort_unstable_by(|a, b| b.cmp(a));
+    inspections.iter().take(2).product()
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+fn lcm(a: u64, b: u64) -> u64 {
+    a / gcd(a, b) * b
+}
+
+/// How worry levels are kept in check after each inspection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Relief {
+    /// Divide by this much, rounding down.
+    Divide(u64),
+    /// Keep only the remainder. Any multiple of every monkey's divisor gives
+    /// the same throws as never reducing at all.
+    Modulo(u64),
+}
+
+impl Relief {
+    fn apply(&self, worry: u64) -> u64 {
+        match *self {
+            Relief::Divide(n) => worry / n,
+            Relief::Modulo(n) => worry % n,
+        }
     }
-    println!("Part1: {}", part1.monkey_business());
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operand {
+    Old,
+    Num(u64),
+}
 
-    for _ in 0..10000 {
-        part2.round(Part::Two(part2.gcd()));
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinOp {
+    Add,
+    Sub,
+    Mul,
+}
+
+/// `new = old <op> <operand>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Expr {
+    op: BinOp,
+    operand: Operand,
+}
+
+impl Expr {
+    /// Works out the new worry level, keeping it below `modulus` if there is
+    /// one. Fails rather than overflowing or going negative.
+    fn eval(&self, old: u64, modulus: Option<u64>) -> anyhow::Result<u64> {
+        let rhs = match self.operand {
+            Operand::Old => old,
+            Operand::Num(n) => n,
+        };
+        let (old, rhs) = (old as u128, rhs as u128);
+        let new = match (self.op, modulus.map(u128::from)) {
+            (BinOp::Add, _) => Some(old + rhs),
+            (BinOp::Mul, _) => Some(old * rhs),
+            (BinOp::Sub, Some(m)) => Some(old + m - rhs % m),
+            (BinOp::Sub, None) => old.checked_sub(rhs),
+        };
+        let new = match (new, modulus) {
+            (Some(new), Some(m)) => Some(new % m as u128),
+            (new, _) => new,
+        };
+        new.and_then(|n| u64::try_from(n).ok())
+            .ok_or_else(|| anyhow!("worry {} is out of range", old))
+    }
+}
+
+impl FromStr for Expr {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words: Vec<&str> = s.split_ascii_whitespace().collect();
+        let ["old", op, operand] = words[..] else {
+            bail!("{:?} isn't of the form old <op> <operand>", s);
+        };
+        let op = match op {
+            "+" => BinOp::Add,
+            "-" => BinOp::Sub,
+            "*" => BinOp::Mul,
+            _ => bail!("unknown operator {:?}", op),
+        };
+        let operand = match operand {
+            "old" => Operand::Old,
+            n => Operand::Num(n.parse()?),
+        };
+        Ok(Expr { op, operand })
     }
-    println!("Part2: {}", part2.monkey_business());
 }
 
 #[derive(Clone, Debug)]
-struct Monkeys(Vec<Monkey>);
+struct Monkey {
+    items: VecDeque<u64>,
+    operation: Expr,
+    divisible: u64,
+    on_true: usize,
+    on_false: usize,
+    inspections: usize,
+}
 
-#[derive(Copy, Clone)]
-enum Part {
-    One,
-    Two(i64),
+impl Monkey {
+    /// Inspects an item, returning its new worry level and who gets it.
+    fn inspect(
+        &self,
+        worry: u64,
+        relief: Relief,
+    ) -> anyhow::Result<(u64, usize)> {
+        let modulus = match relief {
+            Relief::Modulo(m) => Some(m),
+            Relief::Divide(_) => None,
+        };
+        let worry = relief.apply(self.operation.eval(worry, modulus)?);
+        let to = if worry.is_multiple_of(self.divisible) {
+            self.on_true
+        } else {
+            self.on_false
+        };
+        Ok((worry, to))
+    }
 }
 
+#[derive(Clone, Debug)]
+struct Monkeys(Vec<Monkey>);
+
 impl Monkeys {
-    fn gcd(&self) -> i64 {
-        self.0.iter().map(|m| m.divisible).product()
+    /// The smallest worry modulus that doesn't change any throws.
+    fn modulus(&self) -> u64 {
+        self.0.iter().map(|m| m.divisible).fold(1, lcm)
+    }
+
+    fn inspections(&self) -> Vec<usize> {
+        self.0.iter().map(|m| m.inspections).collect()
     }
-    fn round(&mut self, part: Part) {
+
+    /// Plays one round, returning how many items each monkey inspected.
+    fn round(&mut self, relief: Relief) -> anyhow::Result<Vec<usize>> {
+        let mut counts = Vec::with_capacity(self.0.len());
         for i in 0..self.0.len() {
+            let mut count = 0;
             while let Some(worry) = self.0[i].items.pop_front() {
-                self.0[i].inspections += 1;
-                let mut worry = match self.0[i].operation {
-                    Op::Double => worry * 2,
-                    Op::Square => worry * worry,
-                    Op::Add(n) => worry + n,
-                    Op::Mul(n) => worry * n,
-                };
-                match part {
-                    Part::One => worry /= 3,
-                    Part::Two(gcd) => worry %= gcd,
+                count += 1;
+                let (worry, to) = self.0[i].inspect(worry, relief)?;
+                self.0[to].items.push_back(worry);
+            }
+            self.0[i].inspections += count;
+            counts.push(count);
+        }
+        Ok(counts)
+    }
+
+    /// Plays `rounds` rounds, returning the inspection counts from each.
+    fn run(
+        &mut self,
+        rounds: usize,
+        relief: Relief,
+    ) -> anyhow::Result<Vec<Vec<usize>>> {
+        (0..rounds).map(|_| self.round(relief)).collect()
+    }
+
+    /// Follows one item through a round, counting who inspects it. Monkeys
+    /// take turns in order, so the item keeps moving until it's thrown back
+    /// to a monkey that has already had its turn.
+    fn item_round(
+        &self,
+        (mut holder, mut worry): (usize, u64),
+        relief: Relief,
+        counts: &mut [usize],
+    ) -> anyhow::Result<(usize, u64)> {
+        loop {
+            counts[holder] += 1;
+            let (new, to) = self.0[holder].inspect(worry, relief)?;
+            worry = new;
+            if to <= holder {
+                return Ok((to, worry));
+            }
+            holder = to;
+        }
+    }
+
+    /// The total inspections by each monkey after `rounds` rounds, without
+    /// playing them all. Items never affect each other, so each one's route
+    /// is followed on its own until it repeats, and the repeating part is
+    /// then counted in bulk.
+    fn fast_forward(
+        &self,
+        rounds: usize,
+        relief: Relief,
+    ) -> anyhow::Result<Vec<usize>> {
+        let n = self.0.len();
+        let mut totals: Vec<usize> =
+            self.0.iter().map(|m| m.inspections).collect();
+        let items = self.0.iter().enumerate().flat_map(|(holder, m)| {
+            m.items.iter().map(move |&worry| (holder, worry))
+        });
+        for item in items {
+            // Inspection counts summed over the first `r` rounds are at
+            // `prefix[r * n..][..n]`.
+            let mut prefix = vec![0; n];
+            let mut seen = HashMap::from([(item, 0)]);
+            let mut state = item;
+            let mut done = 0;
+            let cycle = loop {
+                if done == rounds {
+                    break None;
+                }
+                let mut counts = prefix[done * n..].to_vec();
+                state = self.item_round(state, relief, &mut counts)?;
+                prefix.extend(counts);
+                done += 1;
+                if let Some(start) = seen.insert(state, done) {
+                    break Some(start);
                 }
-                let throw = if worry % self.0[i].divisible == 0 {
-                    self.0[i].on_true
-                } else {
-                    self.0[i].on_false
+            };
+            let at = |r: usize, m: usize| prefix[r * n + m];
+            for (m, total) in totals.iter_mut().enumerate() {
+                *total += match cycle {
+                    None => at(done, m),
+                    Some(start) => {
+                        let period = done - start;
+                        let (cycles, rest) = (
+                            (rounds - start) / period,
+                            (rounds - start) % period,
+                        );
+                        at(start + rest, m)
+                            + cycles * (at(done, m) - at(start, m))
+                    }
                 };
-                self.0[throw].items.push_back(worry);
             }
         }
+        Ok(totals)
     }
-    fn monkey_business(&self) -> usize {
-        let mut inspections: Vec<usize> =
-            self.0.iter().map(|m| m.inspections).collect();
-        inspections.sort();
-        inspections.reverse();
-        inspections[0] * inspections[1]
+}
+
+input::scan! {
+    #[format("Monkey {id}:")]
+    struct Header {
+        id: usize,
     }
 }
 
-#[derive(Clone, Copy, Debug)]
-enum Op {
-    Add(i64),
-    Mul(i64),
-    Square,
-    Double,
+input::scan! {
+    #[format("Starting items: {items:, }")]
+    struct Items {
+        items: Vec<u64>,
+    }
 }
-#[derive(Clone, Debug)]
-struct Monkey {
-    items: VecDeque<i64>,
-    operation: Op,
-    divisible: i64,
-    on_true: usize,
-    on_false: usize,
-    inspections: usize,
+
+input::scan! {
+    #[format("Operation: new = {operation}")]
+    struct Operation {
+        operation: Expr,
+    }
+}
+
+input::scan! {
+    #[format("Test: divisible by {n}")]
+    struct Test {
+        n: u64,
+    }
+}
+
+input::scan! {
+    #[format("If true: throw to monkey {to}")]
+    struct OnTrue {
+        to: usize,
+    }
+}
+
+input::scan! {
+    #[format("If false: throw to monkey {to}")]
+    struct OnFalse {
+        to: usize,
+    }
+}
+
+/// Parses the next line, with its indentation trimmed, as a `T`. Errors give
+/// the line number.
+fn field<'a, T>(
+    lines: &mut impl Iterator<Item = (usize, &'a str)>,
+) -> anyhow::Result<T>
+where
+    T: FromStr<Err = input::scan::Error>,
+{
+    let (i, line) = lines
+        .next()
+        .ok_or_else(|| anyhow!("unexpected end of the notes"))?;
+    line.trim()
+        .parse()
+        .with_context(|| format!("line {}", i + 1))
 }
 
 impl FromStr for Monkeys {
-    type Err = ();
+    type Err = anyhow::Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let mut monkeys = Vec::new();
-        let mut lines = s.lines();
-        while let Some(monkey) = lines.next() {
-            assert!(monkey.starts_with("Monkey"));
-            let items = lines
-                .next()
-                .unwrap()
-                .trim()
-                .strip_prefix("Starting items: ")
-                .unwrap();
-            let items = input::split(items, ", ");
-            let operation = lines
-                .next()
-                .unwrap()
-                .trim()
-                .strip_prefix("Operation: new = old ")
-                .unwrap();
-            let (op, num): (char, String) = input::split2(operation, " ");
-            let operation = match (op, num.as_str()) {
-                ('*', "old") => Op::Square,
-                ('+', "old") => Op::Double,
-                ('*', _) => Op::Mul(num.parse().unwrap()),
-                ('+', _) => Op::Add(num.parse().unwrap()),
-                _ => {
-                    panic!("Unknown op, num {}, {}", op, num);
-                }
-            };
-            let divisible = lines
-                .next()
-                .unwrap()
-                .trim()
-                .strip_prefix("Test: divisible by ")
-                .unwrap()
-                .parse()
-                .unwrap();
-            let on_true = lines
-                .next()
-                .unwrap()
-                .trim()
-                .strip_prefix("If true: throw to monkey ")
-                .unwrap()
-                .parse()
-                .unwrap();
-            let on_false = lines
-                .next()
-                .unwrap()
-                .trim()
-                .strip_prefix("If false: throw to monkey ")
-                .unwrap()
-                .parse()
-                .unwrap();
-            lines.next();
-            let monkey = Monkey {
-                items: VecDeque::from(items),
+        let mut lines = s.lines().enumerate().filter(|(_, l)| !l.is_empty());
+        while let Some((i, _)) = lines.clone().next() {
+            let Header { id } = field(&mut lines)?;
+            if id != monkeys.len() {
+                bail!("line {}: expected monkey {}", i + 1, monkeys.len());
+            }
+            let Items { items } = field(&mut lines)?;
+            let Operation { operation } = field(&mut lines)?;
+            let Test { n: divisible } = field(&mut lines)?;
+            let OnTrue { to: on_true } = field(&mut lines)?;
+            let OnFalse { to: on_false } = field(&mut lines)?;
+            if divisible == 0 {
+                bail!("monkey {} divides by zero", monkeys.len());
+            }
+            monkeys.push(Monkey {
+                items: items.into(),
                 operation,
                 divisible,
                 on_true,
                 on_false,
                 inspections: 0,
-            };
-            monkeys.push(monkey);
+            });
+        }
+        for (i, m) in monkeys.iter().enumerate() {
+            for to in [m.on_true, m.on_false] {
+                if to == i || to >= monkeys.len() {
+                    bail!("monkey {} can't throw to monkey {}", i, to);
+                }
+            }
         }
         Ok(Monkeys(monkeys))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "Monkey 0:
+  Starting items: 79, 98
+  Operation: new = old * 19
+  Test: divisible by 23
+    If true: throw to monkey 2
+    If false: throw to monkey 3
+
+Monkey 1:
+  Starting items: 54, 65, 75, 74
+  Operation: new = old + 6
+  Test: divisible by 19
+    If true: throw to monkey 2
+    If false: throw to monkey 0
+
+Monkey 2:
+  Starting items: 79, 60, 97
+  Operation: new = old * old
+  Test: divisible by 13
+    If true: throw to monkey 1
+    If false: throw to monkey 3
+
+Monkey 3:
+  Starting items: 74
+  Operation: new = old + 3
+  Test: divisible by 17
+    If true: throw to monkey 0
+    If false: throw to monkey 1
+";
+
+    #[test]
+    fn test_rounds() {
+        let mut monkeys: Monkeys = EXAMPLE.parse().unwrap();
+        let history = monkeys.run(20, Relief::Divide(3)).unwrap();
+        assert_eq!(history[0], vec![2, 4, 3, 5]);
+        assert_eq!(monkeys.inspections(), vec![101, 95, 7, 105]);
+        assert_eq!(monkey_business(&monkeys.inspections()), 10605);
+
+        let mut monkeys: Monkeys = EXAMPLE.parse().unwrap();
+        let relief = Relief::Modulo(monkeys.modulus());
+        monkeys.run(10000, relief).unwrap();
+        assert_eq!(monkeys.inspections(), vec![52166, 47830, 1938, 52013]);
+    }
+
+    #[test]
+    fn test_fast_forward() {
+        let monkeys: Monkeys = EXAMPLE.parse().unwrap();
+        // With the example's modulus no item takes more than 448 rounds to
+        // repeat, so this covers several whole cycles plus a partial one.
+        let reliefs = [
+            (Relief::Divide(3), 20),
+            (Relief::Modulo(monkeys.modulus()), 5 * 448 + 123),
+        ];
+        for (relief, max) in reliefs {
+            for rounds in [0, 1, max] {
+                let mut slow = monkeys.clone();
+                slow.run(rounds, relief).unwrap();
+                assert_eq!(
+                    monkeys.fast_forward(rounds, relief).unwrap(),
+                    slow.inspections(),
+                    "{:?} {}",
+                    relief,
+                    rounds
+                );
+            }
+        }
+        let relief = Relief::Modulo(monkeys.modulus());
+        let fast = monkeys.fast_forward(10000, relief).unwrap();
+        assert_eq!(fast, vec![52166, 47830, 1938, 52013]);
+    }
+
+    #[test]
+    fn test_expr() {
+        let e: Expr = "old - 5".parse().unwrap();
+        assert_eq!(e.eval(7, None).unwrap(), 2);
+        assert!(e.eval(3, None).is_err());
+        assert_eq!(e.eval(3, Some(7)).unwrap(), 5);
+        let e: Expr = "old * old".parse().unwrap();
+        assert_eq!(e.eval(u32::MAX as u64 + 1, Some(10)).unwrap(), 6);
+        assert!(e.eval(u32::MAX as u64 + 1, None).is_err());
+        assert!("old / 2".parse::<Expr>().is_err());
+        assert_eq!(lcm(4, 6), 12);
+    }
+
+    #[test]
+    fn test_fields() {
+        let Header { id } = "Monkey 12:".parse().unwrap();
+        assert_eq!(id, 12);
+        let Items { items } = "Starting items: 79, 98".parse().unwrap();
+        assert_eq!(items, vec![79, 98]);
+        let Operation { operation } =
+            "Operation: new = old * 19".parse().unwrap();
+        assert_eq!(operation.eval(2, None).unwrap(), 38);
+        let Test { n } = "Test: divisible by 23".parse().unwrap();
+        assert_eq!(n, 23);
+        let OnTrue { to } = "If true: throw to monkey 2".parse().unwrap();
+        assert_eq!(to, 2);
+        let OnFalse { to } = "If false: throw to monkey 3".parse().unwrap();
+        assert_eq!(to, 3);
+        assert!("If true: throw to monkey 3".parse::<OnFalse>().is_err());
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = |s: &str| s.parse::<Monkeys>().unwrap_err().to_string();
+        let bad = EXAMPLE.replace("old + 6", "old ^ 6");
+        assert_eq!(err(&bad), "line 10");
+        let bad = EXAMPLE.replace("divisible by 13", "divisible by x");
+        assert_eq!(err(&bad), "line 18");
+        let bad = EXAMPLE.replace("  Test", "  Tset");
+        let full = format!("{:#}", bad.parse::<Monkeys>().unwrap_err());
+        assert_eq!(err(&bad), "line 4");
+        assert!(full.starts_with("line 4: expected \"Test: divisible by \""));
+        let bad = EXAMPLE.replace("Monkey 1:", "Monkey 7:");
+        assert_eq!(err(&bad), "line 8: expected monkey 1");
+        let bad = EXAMPLE.replace("throw to monkey 3", "throw to monkey 2");
+        assert!(bad.parse::<Monkeys>().is_err());
+    }
+}
@@ -2,15 +2,24 @@
 const INPUT: &str = "input/day12.txt";
 use std::{collections::VecDeque, str::FromStr};
 
-use anyhow::anyhow;
+use anyhow::{anyhow, bail};
 use input::{parse_str_lines, CharVec};
-use util::pos::{Pos, PosMap, PosSet};
+use util::pos::{Pos, PosMap};
 
 pub fn solution() {
     let map: Heights = input::parse(INPUT);
-    let part1 = map.ascend();
-    let part2 = map.descend();
+    let route = map.search([map.begin], climbable).unwrap();
+    let part2 = map.search(map.lowest(), climbable).unwrap().len();
+    let part1 = route.len();
     println!("Part1: {part1}\nPart2: {part2}");
+    if crate::options().iter().any(|o| o == "render") {
+        print!("{}", map.render(&route));
+    }
+}
+
+/// The puzzle's climbing rule: at most one step up, any number down.
+fn climbable(from: u8, to: u8) -> bool {
+    to <= from + 1
 }
 
 #[derive(Default, Debug)]
@@ -20,91 +29,94 @@ struct Heights {
     heights: PosMap<u8>,
 }
 
-struct Path {
-    len: usize,
-    pos: Pos,
+/// A route through the map, including both ends.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Path(Vec<Pos>);
+
+impl Path {
+    /// The number of steps taken.
+    fn len(&self) -> usize {
+        self.0.len() - 1
+    }
 }
 
 impl Heights {
-    fn ascend(&self) -> usize {
-        let mut visited = PosSet::new();
+    /// Every cell at the lowest height.
+    fn lowest(&self) -> impl Iterator<Item = Pos> + '_ {
+        self.heights
+            .iter()
+            .filter(|(_, h)| **h == 0)
+            .map(|(p, _)| *p)
+    }
+
+    /// Finds a shortest route to the end from any of `starts`, only stepping
+    /// from one height to another if `can_step` allows it.
+    fn search(
+        &self,
+        starts: impl IntoIterator<Item = Pos>,
+        can_step: impl Fn(u8, u8) -> bool,
+    ) -> Option<Path> {
+        // Where each visited cell was reached from. Starts come from nowhere.
+        let mut came_from: PosMap<Option<Pos>> = PosMap::new();
         let mut queue = VecDeque::new();
-        queue.push_back(Path {
-            len: 0,
-            pos: self.begin,
-        });
-        visited.insert(self.begin);
-        while let Some(path) = queue.pop_front() {
-            // Base case: we reached the end!
-            if path.pos == self.end {
-                // BFS means this is the shortest path.
-                return path.len;
+        for s in starts {
+            if self.heights.contains_key(&s) && !came_from.contains_key(&s) {
+                came_from.insert(s, None);
+                queue.push_back(s);
+            }
+        }
+        while let Some(pos) = queue.pop_front() {
+            // BFS means the first time we get here is the shortest way.
+            if pos == self.end {
+                let mut path = vec![pos];
+                while let Some(prev) = came_from[path.last().unwrap()] {
+                    path.push(prev);
+                }
+                path.reverse();
+                return Some(Path(path));
             }
 
-            // Visit reachable neighbors that aren't already in the queue.
-            let current_height = self.heights.get(&path.pos).unwrap();
-            for n in path.pos.compass_neighbors() {
-                if visited.contains(&n) {
+            let height = self.heights[&pos];
+            for n in pos.compass_neighbors() {
+                let Some(&h) = self.heights.get(&n) else {
                     continue;
-                }
-                if let Some(h) = self.heights.get(&n) {
-                    let dh = *h as i8 - *current_height as i8;
-                    if dh > 1 {
-                        continue;
-                    }
-                    queue.push_back(Path {
-                        len: path.len + 1,
-                        pos: n,
-                    });
-                    visited.insert(n);
+                };
+                if can_step(height, h) && !came_from.contains_key(&n) {
+                    came_from.insert(n, Some(pos));
+                    queue.push_back(n);
                 }
             }
         }
-        panic!(
-            "Couldn't find a path from {:?} to {:?}",
-            self.begin, self.end
-        )
+        None
     }
 
-    fn descend(&self) -> usize {
-        let mut visited = PosSet::new();
-        let mut queue = VecDeque::new();
-        queue.push_back(Path {
-            len: 0,
-            pos: self.end,
-        });
-        visited.insert(self.begin);
-        while let Some(path) = queue.pop_front() {
-            let current_height = self.heights.get(&path.pos).unwrap();
-
-            // Base case: we found the closest 'a'.
-            if *current_height == 0 {
-                // BFS means this is the shortest path.
-                return path.len;
-            }
-
-            // Visit reachable neighbors that aren't already in the queue.
-            for n in path.pos.compass_neighbors() {
-                if visited.contains(&n) {
-                    continue;
-                }
-                if let Some(h) = self.heights.get(&n) {
-                    let dh = *current_height as i8 - *h as i8;
-                    if dh > 1 {
-                        continue;
-                    }
-                    queue.push_back(Path {
-                        len: path.len + 1,
-                        pos: n,
-                    });
-                    visited.insert(n);
-                }
+    /// Draws a route the way the puzzle does, with an arrow on each cell
+    /// pointing to the next one and `E` at the end.
+    fn render(&self, Path(path): &Path) -> String {
+        let mut arrows: PosMap<char> = PosMap::new();
+        for pair in path.windows(2) {
+            let arrow = match pair[1] - pair[0] {
+                Pos(1, 0) => '>',
+                Pos(-1, 0) => '<',
+                Pos(0, -1) => '^',
+                Pos(0, 1) => 'v',
+                d => unreachable!("{:?} isn't a single step", d),
+            };
+            arrows.insert(pair[0], arrow);
+        }
+        if let Some(end) = path.last() {
+            arrows.insert(*end, 'E');
+        }
+        let max_x = self.heights.keys().map(|p| p.0).max().unwrap_or(-1);
+        let max_y = self.heights.keys().map(|p| p.1).max().unwrap_or(-1);
+        let mut out = String::new();
+        for y in 0..=max_y {
+            for x in 0..=max_x {
+                out.push(*arrows.get(&Pos(x, y)).unwrap_or(&'.'));
             }
+            out.push('\n');
         }
-        panic!(
-            "Couldn't find a path from {:?} to {:?}",
-            self.begin, self.end
-        )
+        out
     }
 }
 
@@ -113,26 +125,78 @@ impl FromStr for Heights {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let mut map = Heights::default();
+        let (mut begin, mut end) = (None, None);
         let heights: Vec<CharVec<char>> = parse_str_lines(s);
         for (y, CharVec(h)) in heights.into_iter().enumerate() {
             for (x, c) in h.into_iter().enumerate() {
                 let pos = Pos(x as i64, y as i64);
                 match c {
                     'S' => {
-                        map.begin = pos;
+                        if begin.replace(pos).is_some() {
+                            bail!(
+                                "expected exactly one S, found another at {:?}",
+                                pos
+                            );
+                        }
                         map.heights.insert(pos, 0);
                     }
                     'E' => {
-                        map.end = pos;
-                        map.heights.insert(pos, 'z' as u8 - 'a' as u8);
+                        if end.replace(pos).is_some() {
+                            bail!(
+                                "expected exactly one E, found another at {:?}",
+                                pos
+                            );
+                        }
+                        map.heights.insert(pos, b'z' - b'a');
                     }
                     'a'..='z' => {
-                        map.heights.insert(pos, c as u8 - 'a' as u8);
+                        map.heights.insert(pos, c as u8 - b'a');
                     }
                     _ => return Err(anyhow!("Invalid char {:?}", c)),
                 }
             }
         }
+        map.begin = begin.ok_or_else(|| anyhow!("expected exactly one S"))?;
+        map.end = end.ok_or_else(|| anyhow!("expected exactly one E"))?;
         Ok(map)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "Sabqponm\nabcryxxl\naccszExk\nacctuvwj\nabdefghi";
+
+    #[test]
+    fn test_search() {
+        let map: Heights = EXAMPLE.parse().unwrap();
+        let path = map.search([map.begin], climbable).unwrap();
+        assert_eq!(path.len(), 31);
+        // One of several equally short routes.
+        assert_eq!(
+            map.render(&path),
+            "\
+>>vv<<<<
+..vvv<<^
+..vv>E^^
+..v>>>^^
+..>>>>>^
+"
+        );
+        assert_eq!(map.search(map.lowest(), climbable).unwrap().len(), 29);
+    }
+
+    #[test]
+    fn test_unreachable() {
+        let map: Heights = "SazE".parse().unwrap();
+        assert_eq!(map.search([map.begin], climbable), None);
+        // Anything goes.
+        let path = map.search([map.begin], |_, _| true).unwrap();
+        assert_eq!(path.0, vec![Pos(0, 0), Pos(1, 0), Pos(2, 0), Pos(3, 0)]);
+        assert!("SabS\nE".parse::<Heights>().is_err());
+        assert!("Sab".parse::<Heights>().is_err());
+        assert!("SaSbS\nE".parse::<Heights>().is_err());
+        assert!("SaEbE\nE".parse::<Heights>().is_err());
+    }
+}
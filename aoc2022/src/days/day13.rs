@@ -1,153 +1,273 @@
-use std::{cmp::Ordering, collections::VecDeque};
+use std::{cmp::Ordering, fmt::Display, slice, str::FromStr};
 
-use pest::{iterators::Pairs, Parser};
-use pest_derive::Parser;
+use anyhow::{anyhow, bail, Context};
 
 const INPUT: &str = "input/day13.txt";
 
-#[derive(Parser)]
-#[grammar_inline = r#"
-number  =  { ASCII_DIGIT+ }
-value   = _{ number | list }
-list    =  { "[]" | "[" ~ value ~ ("," ~ value)* ~ "]" }
-pair    =  { (list ~ NEWLINE ~ list ~ NEWLINE*) }
-packets =  { pair+ }
-"#]
-struct PacketParser;
-
 pub fn solution() {
     let raw = input::string(INPUT);
-    let packets = PacketParser::parse(Rule::packets, &raw)
-        .unwrap()
-        .next()
-        .unwrap();
-    let mut pairs = Vec::new();
-    for pair in packets.into_inner() {
-        let mut pair = pair.into_inner();
-        let left = pair.next().unwrap().into_inner();
-        let right = pair.next().unwrap().into_inner();
-        pairs.push(Pair(Packet::from(left), Packet::from(right)));
-    }
-    let mut part1 = 0;
-    for (i, pair) in pairs.iter().enumerate() {
-        if pair.in_order() {
-            part1 += i + 1;
-        }
-    }
-    println!("Part1: {part1}");
+    let pairs = parse_pairs(&raw).unwrap();
 
-    // Break the packets out of pairs to sort them.
-    let mut packets = Vec::new();
-    for Pair(lhs, rhs) in pairs {
-        packets.push(lhs);
-        packets.push(rhs);
-    }
-    // Add the special divider packets...
-    let begin = Packet(vec![
-        Token::Open,
-        Token::Open,
-        Token::Num(2),
-        Token::Close,
-        Token::Close,
-    ]);
-    let end = Packet(vec![
-        Token::Open,
-        Token::Open,
-        Token::Num(6),
-        Token::Close,
-        Token::Close,
-    ]);
-    packets.push(begin.clone());
-    packets.push(end.clone());
-    packets.sort_unstable_by(compare_packets);
-    let begin = packets.iter().position(|p| p == &begin).unwrap();
-    let end = packets.iter().position(|p| p == &end).unwrap();
-    let part2 = (begin + 1) * (end + 1);
-    println!("Part2: {part2}");
+    let part1: usize = pairs
+        .iter()
+        .enumerate()
+        .filter(|(_, (left, right))| left < right)
+        .map(|(i, _)| i + 1)
+        .sum();
+    println!("Part1: {}", part1);
+
+    let packets: Vec<PacketValue> =
+        pairs.into_iter().flat_map(|(l, r)| [l, r]).collect();
+    let part2 = decoder_key(&packets);
+    println!("Part2: {}", part2);
+}
+
+/// The product of the dividers' indices, counting from 1, once they're
+/// added to `packets` and everything is sorted. Each divider's index is one
+/// more than the number of packets before it, so there's no need to sort
+/// anything.
+fn decoder_key(packets: &[PacketValue]) -> usize {
+    let [first, second] = dividers();
+    let before = |d: &PacketValue| packets.iter().filter(|&p| p < d).count();
+    (before(&first) + 1) * (before(&second) + 2)
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Token {
-    Open,
-    Close,
-    Num(i64),
+/// The packets that part 2 adds, in order.
+fn dividers() -> [PacketValue; 2] {
+    ["[[2]]".parse().unwrap(), "[[6]]".parse().unwrap()]
+}
+
+fn parse_pairs(s: &str) -> anyhow::Result<Vec<(PacketValue, PacketValue)>> {
+    s.replace("\r\n", "\n")
+        .trim_end()
+        .split("\n\n")
+        .map(|pair| {
+            let lines: Vec<&str> = pair.lines().collect();
+            let [left, right] = lines[..] else {
+                bail!("expected 2 packets, found {}", lines.len());
+            };
+            Ok((left.parse()?, right.parse()?))
+        })
+        .enumerate()
+        .map(|(i, pair)| pair.with_context(|| format!("pair {}", i + 1)))
+        .collect()
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-struct Packet(Vec<Token>);
-
-impl From<Pairs<'_, Rule>> for Packet {
-    fn from(value: Pairs<'_, Rule>) -> Self {
-        let mut tokens = Vec::new();
-        tokens.push(Token::Open);
-        for v in value {
-            match v.as_rule() {
-                Rule::number => {
-                    let n = v.as_str();
-                    tokens.push(Token::Num(n.parse().unwrap()))
-                }
-                Rule::list => {
-                    let Packet(mut inner_tokens) = Packet::from(v.into_inner());
-                    tokens.append(&mut inner_tokens);
-                }
-                _ => unreachable!(),
-            }
+enum PacketValue {
+    Int(u64),
+    List(Vec<PacketValue>),
+}
+
+impl Ord for PacketValue {
+    fn cmp(&self, other: &Self) -> Ordering {
+        use PacketValue::*;
+        match (self, other) {
+            (Int(a), Int(b)) => a.cmp(b),
+            (List(a), List(b)) => a.cmp(b),
+            // Compare a lone integer as a list holding just that integer.
+            (Int(_), List(b)) => slice::from_ref(self).cmp(b),
+            (List(a), Int(_)) => a.as_slice().cmp(slice::from_ref(other)),
         }
-        tokens.push(Token::Close);
-        Packet(tokens)
     }
 }
 
-#[derive(Debug)]
-struct Pair(Packet, Packet);
-
-fn compare_packets(Packet(lhs): &Packet, Packet(rhs): &Packet) -> Ordering {
-    use Token::*;
-
-    let mut lhs = VecDeque::from(lhs.clone());
-    let mut rhs = VecDeque::from(rhs.clone());
-    loop {
-        match (lhs.pop_front(), rhs.pop_front()) {
-            (None, None) => return Ordering::Equal,
-            (None, _) => return Ordering::Less,
-            (_, None) => return Ordering::Greater,
-            // Same element types...
-            (Some(Open), Some(Open)) => (),
-            (Some(Close), Some(Close)) => (),
-            (Some(Num(a)), Some(Num(b))) => {
-                if a > b {
-                    return Ordering::Greater;
-                }
-                if a < b {
-                    return Ordering::Less;
+impl PartialOrd for PacketValue {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for PacketValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PacketValue::Int(n) => write!(f, "{}", n),
+            PacketValue::List(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", item)?;
                 }
+                write!(f, "]")
             }
-            // rhs runs out of elements first means not in order.
-            (Some(Num(_)), Some(Close)) | (Some(Open), Some(Close)) => {
-                return Ordering::Greater
-            }
-            // lhs runs out of elements is in order.
-            (Some(Close), Some(Num(_))) | (Some(Close), Some(Open)) => {
-                return Ordering::Less;
-            }
-            // Exactly one of the elements is a number. Lift it into a list.
-            (Some(Num(n)), Some(Open)) => {
-                lhs.push_front(Token::Close);
-                lhs.push_front(Num(n));
+        }
+    }
+}
+
+impl FromStr for PacketValue {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let mut parser = PacketParser {
+            bytes: s.as_bytes(),
+            at: 0,
+        };
+        let value = parser.value()?;
+        if parser.at != s.len() {
+            bail!("trailing {:?} at offset {}", &s[parser.at..], parser.at);
+        }
+        Ok(value)
+    }
+}
+
+/// A recursive descent parser for a single packet value.
+struct PacketParser<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl PacketParser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.at).copied()
+    }
+
+    fn expect(&mut self, b: u8) -> anyhow::Result<()> {
+        match self.peek() {
+            Some(found) if found == b => {
+                self.at += 1;
+                Ok(())
             }
-            (Some(Open), Some(Num(n))) => {
-                rhs.push_front(Token::Close);
-                rhs.push_front(Num(n));
+            found => bail!(
+                "expected {:?} at offset {}, found {:?}",
+                b as char,
+                self.at,
+                found.map(char::from)
+            ),
+        }
+    }
+
+    fn value(&mut self) -> anyhow::Result<PacketValue> {
+        match self.peek() {
+            Some(b'[') => self.list(),
+            Some(b'0'..=b'9') => self.int(),
+            found => bail!(
+                "expected a value at offset {}, found {:?}",
+                self.at,
+                found.map(char::from)
+            ),
+        }
+    }
+
+    fn int(&mut self) -> anyhow::Result<PacketValue> {
+        let start = self.at;
+        while self.peek().is_some_and(|b| b.is_ascii_digit()) {
+            self.at += 1;
+        }
+        let digits = std::str::from_utf8(&self.bytes[start..self.at])?;
+        Ok(PacketValue::Int(digits.parse()?))
+    }
+
+    fn list(&mut self) -> anyhow::Result<PacketValue> {
+        self.expect(b'[')?;
+        let mut items = Vec::new();
+        if self.peek() == Some(b']') {
+            self.at += 1;
+            return Ok(PacketValue::List(items));
+        }
+        loop {
+            items.push(self.value()?);
+            match self.peek() {
+                Some(b',') => self.at += 1,
+                _ => break,
             }
         }
+        self.expect(b']')?;
+        Ok(PacketValue::List(items))
+    }
+}
+
+/// Packets happen to be valid JSON, as long as they only use arrays and
+/// non-negative integers.
+impl TryFrom<&serde_json::Value> for PacketValue {
+    type Error = anyhow::Error;
+    fn try_from(value: &serde_json::Value) -> Result<Self, Self::Error> {
+        match value {
+            serde_json::Value::Number(n) => n
+                .as_u64()
+                .map(PacketValue::Int)
+                .ok_or_else(|| anyhow!("{} isn't a non-negative integer", n)),
+            serde_json::Value::Array(items) => items
+                .iter()
+                .map(PacketValue::try_from)
+                .collect::<Result<_, _>>()
+                .map(PacketValue::List),
+            _ => bail!("{} isn't an array or an integer", value),
+        }
     }
 }
 
-impl Pair {
-    fn in_order(&self) -> bool {
-        matches!(
-            compare_packets(&self.0, &self.1),
-            Ordering::Less | Ordering::Equal
-        )
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "[1,1,3,1,1]
+[1,1,5,1,1]
+
+[[1],[2,3,4]]
+[[1],4]
+
+[9]
+[[8,7,6]]
+
+[[4,4],4,4]
+[[4,4],4,4,4]
+
+[7,7,7,7]
+[7,7,7]
+
+[]
+[3]
+
+[[[]]]
+[[]]
+
+[1,[2,[3,[4,[5,6,7]]]],8,9]
+[1,[2,[3,[4,[5,6,0]]]],8,9]
+";
+
+    #[test]
+    fn test_order() {
+        let pairs = parse_pairs(EXAMPLE).unwrap();
+        let ordered: Vec<_> = pairs.iter().map(|(l, r)| l < r).collect();
+        assert_eq!(
+            ordered,
+            vec![true, true, false, true, false, true, false, false]
+        );
+
+        let packets: Vec<_> =
+            pairs.into_iter().flat_map(|(l, r)| [l, r]).collect();
+        assert_eq!(decoder_key(&packets), 140);
+        assert_eq!(decoder_key(&[]), 2);
+    }
+
+    #[test]
+    fn test_display() {
+        for line in EXAMPLE.lines().filter(|l| !l.is_empty()) {
+            let packet: PacketValue = line.parse().unwrap();
+            assert_eq!(packet.to_string(), line);
+        }
+    }
+
+    #[test]
+    fn test_json() {
+        for line in EXAMPLE.lines().filter(|l| !l.is_empty()) {
+            let json: serde_json::Value = serde_json::from_str(line).unwrap();
+            assert_eq!(
+                PacketValue::try_from(&json).unwrap(),
+                line.parse().unwrap()
+            );
+        }
+        let json = serde_json::json!([1, [2, "three"]]);
+        assert!(PacketValue::try_from(&json).is_err());
+    }
+
+    #[test]
+    fn test_errors() {
+        for bad in ["", "[", "[1,]", "[1]]", "[1 2]", "[-1]", "x"] {
+            assert!(bad.parse::<PacketValue>().is_err(), "{:?}", bad);
+        }
+        assert!(parse_pairs("[1]\n[2]\n\n[3]").is_err());
     }
 }
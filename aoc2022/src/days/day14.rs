@@ -1,142 +1,288 @@
-use pest::Parser;
-use pest_derive::Parser;
-use std::str::FromStr;
+use std::fmt::Display;
 
-use util::pos::{Pos, PosMap};
+use anyhow::bail;
+use util::pos::Pos;
 
 const INPUT: &str = "input/day14.txt";
+const SOURCE: Pos = Pos(500, 0);
 
 pub fn solution() {
     let paths: Vec<RockPath> = input::parse_lines(INPUT);
-    let mut cave = Cave::from(paths.as_slice());
-    let mut part1 = 0;
-    while cave.pour(Part::One).is_some() {
-        part1 += 1;
-    }
+    let mut sim = SandSim::new(&paths, SOURCE, Bottom::Abyss).unwrap();
+    let part1 = sim.fill();
     println!("Part1: {part1}");
 
-    let mut cave = Cave::from(paths.as_slice());
-    let mut part2 = 0;
-    while let Some(pos) = cave.pour(Part::Two) {
-        part2 += 1;
-        if pos == Pos(500, 0) {
-            break;
-        }
-    }
+    // Everything that came to rest above the abyss rests there with a floor
+    // too, so we can carry on from here.
+    sim.bottom = Bottom::Floor;
+    let part2 = part1 + sim.fill();
+    debug_assert_eq!(part2, sim.floored_capacity());
     println!("Part2: {part2}");
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Cell {
+    Air,
     Rock,
     Sand,
 }
 
+/// What's below the lowest rock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bottom {
+    /// Nothing, so sand that falls past the lowest rock is lost.
+    Abyss,
+    /// An endless floor two below the lowest rock.
+    Floor,
+}
+
+/// Sand pouring into a cave, one grain at a time.
 #[derive(Debug, Clone)]
-struct Cave {
-    cells: PosMap<Cell>,
+struct SandSim {
+    /// Every cell from the source down to just above the floor, and as far
+    /// to each side as sand could ever reach.
+    cells: Vec<Cell>,
+    width: usize,
+    /// The top left corner of `cells`.
+    origin: Pos,
+    /// The lowest rock.
     y_max: i64,
+    source: Pos,
+    bottom: Bottom,
+    /// The route the last grain took, minus its resting place. The next
+    /// grain follows the same route, so it can start from the end of it.
+    path: Vec<Pos>,
 }
 
-enum Part {
-    One,
-    Two,
-}
+impl SandSim {
+    /// Builds the cave from rock paths. Rocks above the source can never be
+    /// reached, so they're left out.
+    fn new(
+        paths: &[RockPath],
+        source: Pos,
+        bottom: Bottom,
+    ) -> anyhow::Result<Self> {
+        let mut rocks = Vec::new();
+        for RockPath { points: path } in paths {
+            for pair in path.windows(2) {
+                let (p1, p2) = (pair[0].min(pair[1]), pair[0].max(pair[1]));
+                let iter = match p2 - p1 {
+                    Pos(0, _) => p1.iter_y(p2.1),
+                    Pos(_, 0) => p1.iter_x(p2.0),
+                    _ => bail!("{} -> {} isn't a straight line", p1, p2),
+                };
+                rocks.extend(iter.filter(|p| p.1 >= source.1));
+            }
+        }
+        let y_max = rocks.iter().map(|p| p.1).max().unwrap_or(source.1);
+
+        // With a floor, sand spreads out at most one cell per row, in a
+        // triangle under the source.
+        let spread = y_max + 2 - source.1;
+        let x_min = rocks.iter().map(|p| p.0).min().unwrap_or(source.0);
+        let x_max = rocks.iter().map(|p| p.0).max().unwrap_or(source.0);
+        let origin = Pos(x_min.min(source.0 - spread), source.1);
+        let width = (x_max.max(source.0 + spread) - origin.0 + 1) as usize;
+        let height = spread as usize;
 
-impl Cave {
-    fn pour(&mut self, part: Part) -> Option<Pos> {
-        let mut x = 500;
-        let y_max = match part {
-            Part::One => self.y_max,
-            Part::Two => self.y_max + 2,
+        let mut sim = SandSim {
+            cells: vec![Cell::Air; width * height],
+            width,
+            origin,
+            y_max,
+            source,
+            bottom,
+            path: vec![source],
         };
-        for y in 0..y_max {
-            let (left, down, right) =
-                if matches!(part, Part::Two) && y + 1 == y_max {
-                    // Quick, fill in the floor!
-                    (Some(&Cell::Rock), Some(&Cell::Rock), Some(&Cell::Rock))
-                } else {
-                    (
-                        self.cells.get(&Pos(x - 1, y + 1)),
-                        self.cells.get(&Pos(x, y + 1)),
-                        self.cells.get(&Pos(x + 1, y + 1)),
-                    )
-                };
-            match (left, down, right) {
-                (_, None, _) => {
-                    // Keep falling down...
-                }
-                (None, Some(_), _) => {
-                    // Move to the left...
-                    x -= 1;
-                }
-                (Some(_), Some(_), None) => {
-                    // Move to the right...
-                    x += 1;
-                }
-                (Some(_), Some(_), Some(_)) => {
-                    // Come to rest!
-                    self.cells.insert(Pos(x, y), Cell::Sand);
-                    return Some(Pos(x, y));
+        for p in rocks {
+            let i = sim.index(p).unwrap();
+            sim.cells[i] = Cell::Rock;
+        }
+        Ok(sim)
+    }
+
+    fn index(&self, p: Pos) -> Option<usize> {
+        let Pos(x, y) = p - self.origin;
+        let height = (self.cells.len() / self.width) as i64;
+        if (0..self.width as i64).contains(&x) && (0..height).contains(&y) {
+            Some(y as usize * self.width + x as usize)
+        } else {
+            None
+        }
+    }
+
+    fn get(&self, p: Pos) -> Cell {
+        match self.index(p) {
+            Some(i) => self.cells[i],
+            // Below the grid is the floor. Nothing else is reachable.
+            None => Cell::Rock,
+        }
+    }
+
+    /// Drops one grain, returning where it came to rest. Returns `None`
+    /// if it fell into the abyss or the source is already blocked.
+    fn drop_grain(&mut self) -> Option<Pos> {
+        while let Some(&p) = self.path.last() {
+            if self.bottom == Bottom::Abyss && p.1 >= self.y_max {
+                return None;
+            }
+            let next = [Pos(0, 1), Pos(-1, 1), Pos(1, 1)]
+                .into_iter()
+                .map(|d| p + d)
+                .find(|&n| self.get(n) == Cell::Air);
+            match next {
+                Some(n) => self.path.push(n),
+                None => {
+                    let i = self.index(p).unwrap();
+                    self.cells[i] = Cell::Sand;
+                    self.path.pop();
+                    return Some(p);
                 }
             }
         }
-        return None;
+        None
+    }
+
+    /// Drops grains until one doesn't come to rest, returning how many did.
+    fn fill(&mut self) -> usize {
+        std::iter::from_fn(|| self.drop_grain()).count()
+    }
+
+    /// How much sand a floored cave holds when full, without dropping any.
+    /// Sand ends up in exactly the cells reachable from the source by
+    /// falling, so this just searches them row by row.
+    fn floored_capacity(&self) -> usize {
+        let mut total = 0;
+        let mut row = vec![self.source];
+        while !row.is_empty() {
+            total += row.len();
+            let mut next: Vec<Pos> = row
+                .iter()
+                .flat_map(|&p| [p + Pos(-1, 1), p + Pos(0, 1), p + Pos(1, 1)])
+                .filter(|&p| self.get(p) != Cell::Rock)
+                .collect();
+            next.sort();
+            next.dedup();
+            row = next;
+        }
+        total
     }
 }
 
-impl From<&[RockPath]> for Cave {
-    fn from(paths: &[RockPath]) -> Self {
-        let mut y_max = 0;
-        let mut cells = PosMap::new();
-        for RockPath(path) in paths {
-            for pair in path.windows(2) {
-                let p1 = &pair[0];
-                let p2 = &pair[1];
-                y_max = y_max.max(p1.1).max(p2.1);
-                let Pos(dx, dy) = *p1 - *p2;
-                let iter = match (dx, dy) {
-                    (0, dy) if dy > 0 => p2.iter_y(p1.1),
-                    (0, _) => p1.iter_y(p2.1),
-                    (dx, 0) if dx > 0 => p2.iter_x(p1.0),
-                    (_, 0) => p1.iter_x(p2.0),
-                    _ => unreachable!(),
+/// Draws the cave the way the puzzle does, cropped to the rock, the sand
+/// and the source.
+impl Display for SandSim {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut used = (0..self.cells.len())
+            .filter(|&i| self.cells[i] != Cell::Air)
+            .map(|i| {
+                let (x, y) = (i % self.width, i / self.width);
+                self.origin + Pos(x as i64, y as i64)
+            })
+            .chain([self.source]);
+        let first = used.next().unwrap();
+        let (min, max) = used.fold((first, first), |(min, max), p| {
+            (
+                Pos(min.0.min(p.0), min.1.min(p.1)),
+                Pos(max.0.max(p.0), max.1.max(p.1)),
+            )
+        });
+        let floor = match self.bottom {
+            Bottom::Floor => self.y_max + 2,
+            Bottom::Abyss => max.1,
+        };
+        for y in min.1..=floor {
+            for x in min.0..=max.0 {
+                let p = Pos(x, y);
+                let c = match self.get(p) {
+                    _ if p == self.source => '+',
+                    Cell::Rock => '#',
+                    Cell::Sand => 'o',
+                    Cell::Air => '.',
                 };
-                for p in iter {
-                    cells.insert(p, Cell::Rock);
-                }
+                write!(f, "{}", c)?;
             }
+            writeln!(f)?;
         }
-        Cave { cells, y_max }
+        Ok(())
+    }
+}
+
+input::scan! {
+    #[format("{points: -> }")]
+    #[derive(Debug)]
+    struct RockPath {
+        points: Vec<Pos>,
     }
 }
 
-#[derive(Parser)]
-#[grammar_inline = r#"
-num  = { ASCII_DIGIT+ }
-pos  = { num ~ "," ~ num }
-path = { pos ~ (" -> " ~ pos)* }
-"#]
-struct RockPathParser;
-
-#[derive(Debug)]
-struct RockPath(Vec<Pos>);
-
-impl FromStr for RockPath {
-    type Err = anyhow::Error;
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let path = RockPathParser::parse(Rule::path, s)
-            .unwrap()
-            .next()
-            .unwrap()
-            .into_inner();
-        let mut points = Vec::new();
-        for pos in path {
-            let mut pos = pos.into_inner();
-            let x = pos.next().unwrap().as_str();
-            let y = pos.next().unwrap().as_str();
-            points.push(Pos(x.parse().unwrap(), y.parse().unwrap()));
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str =
+        "498,4 -> 498,6 -> 496,6\n503,4 -> 502,4 -> 502,9 -> 494,9";
+
+    fn sim(source: Pos, bottom: Bottom) -> SandSim {
+        let paths: Vec<RockPath> = input::parse_str_lines(EXAMPLE);
+        SandSim::new(&paths, source, bottom).unwrap()
+    }
+
+    #[test]
+    fn test_fill() {
+        let mut abyss = sim(SOURCE, Bottom::Abyss);
+        assert_eq!(abyss.fill(), 24);
+        abyss.bottom = Bottom::Floor;
+        assert_eq!(abyss.fill(), 93 - 24);
+
+        let mut floor = sim(SOURCE, Bottom::Floor);
+        assert_eq!(floor.floored_capacity(), 93);
+        assert_eq!(floor.fill(), 93);
+        assert_eq!(floor.drop_grain(), None);
+    }
+
+    #[test]
+    fn test_source() {
+        // Pouring from right above the left ledge.
+        let mut s = sim(Pos(497, 2), Bottom::Floor);
+        assert_eq!(s.floored_capacity(), s.clone().fill());
+        assert_eq!(s.drop_grain(), Some(Pos(497, 5)));
+
+        // Everything falls off the right hand side.
+        let mut s = sim(Pos(504, 0), Bottom::Abyss);
+        assert_eq!(s.fill(), 0);
+    }
+
+    #[test]
+    fn test_render() {
+        let mut s = sim(SOURCE, Bottom::Abyss);
+        for _ in 0..5 {
+            s.drop_grain();
         }
-        Ok(RockPath(points))
+        let expected = "\
+......+...
+..........
+..........
+..........
+....#...##
+....#...#.
+..###...#.
+......o.#.
+....oooo#.
+#########.
+";
+        assert_eq!(s.to_string(), expected);
+        assert_eq!(s.fill(), 19);
+        let drawn = s.to_string();
+        assert_eq!(drawn.matches('o').count(), 24);
+        assert!(drawn.ends_with("\n#########.\n"));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!("1,2 -> ".parse::<RockPath>().is_err());
+        assert!("1,2 -> 3".parse::<RockPath>().is_err());
+        let diagonal = ["1,2 -> 3,4".parse().unwrap()];
+        assert!(SandSim::new(&diagonal, SOURCE, Bottom::Abyss).is_err());
     }
 }
@@ -1,8 +1,9 @@
 use std::{collections::HashSet, str::FromStr};
 
-use pest::Parser;
-use pest_derive::Parser;
-use util::pos::Pos;
+use util::{
+    interval::{self, Interval},
+    pos::Pos,
+};
 
 const INPUT: &str = "input/day15.txt";
 
@@ -17,8 +18,8 @@ pub fn solution() {
 fn part1(positions: &[Position]) -> usize {
     const ROW: i64 = 2_000_000;
     let mut set = HashSet::new();
-    for (start, end) in row_coverage(positions, ROW) {
-        for i in start..=end {
+    for r in row_coverage(positions, ROW) {
+        for i in r.start()..=r.end() {
             set.insert(i);
         }
     }
@@ -35,12 +36,12 @@ fn part2(positions: &[Position]) -> i64 {
     for y in 0..=ROW {
         let intervals: Vec<_> = row_coverage(positions, y)
             .into_iter()
-            .filter(|(start, end)| *end >= 0 && *start <= ROW)
+            .filter(|r| r.end() >= 0 && r.start() <= ROW)
             .collect();
         if intervals.len() > 1 {
             assert_eq!(intervals.len(), 2);
-            let (_, x1) = intervals[0];
-            let (x2, _) = intervals[1];
+            let x1 = intervals[0].end();
+            let x2 = intervals[1].start();
             assert_eq!(x2 - x1, 2);
             return (x1 + 1) * ROW + y;
         }
@@ -49,29 +50,8 @@ fn part2(positions: &[Position]) -> i64 {
 }
 
 // Returns the row coverage in a list of non-overlapping intervals.
-fn row_coverage(positions: &[Position], y: i64) -> Vec<(i64, i64)> {
-    let mut intervals: Vec<(i64, i64)> =
-        positions.iter().filter_map(|p| p.row_coverage(y)).collect();
-    intervals.sort();
-    let mut merged = Vec::new();
-    let mut start = intervals[0].0;
-    let mut end: i64 = intervals[0].1;
-    for &(cur_start, cur_end) in &intervals[1..] {
-        // If this interval is entirely within the interval
-        // already being built, ignore it.
-        if start <= cur_start && cur_end <= end {
-            continue;
-        }
-        // If this is the end of the interval, add it to the
-        // list and start a new one.
-        if cur_start > end + 1 {
-            merged.push((start, end));
-            start = cur_start
-        }
-        end = cur_end
-    }
-    merged.push((start, end));
-    merged
+fn row_coverage(positions: &[Position], y: i64) -> Vec<Interval> {
+    interval::union(positions.iter().filter_map(|p| p.row_coverage(y)))
 }
 
 #[derive(Debug)]
@@ -82,43 +62,49 @@ struct Position {
 
 impl Position {
     // Returns the x values covered by this sensor in the given row.
-    fn row_coverage(&self, y: i64) -> Option<(i64, i64)> {
+    fn row_coverage(&self, y: i64) -> Option<Interval> {
         let row_distance = self.sensor.1.abs_diff(y);
         let reach = self.sensor.distance(&self.beacon);
         if row_distance > reach {
             return None;
         }
         let delta = i64::try_from(reach - row_distance).unwrap();
-        Some((self.sensor.0 - delta, self.sensor.0 + delta))
+        Interval::new(self.sensor.0 - delta, self.sensor.0 + delta).ok()
     }
 }
 
-#[derive(Parser)]
-#[grammar_inline = r#"
-num       = { "-"? ~ ASCII_DIGIT+ }
-pos       = { "x=" ~ num ~ ", y=" ~ num }
-positions = { "Sensor at " ~ pos ~ ": closest beacon is at " ~ pos }
-"#]
-struct PositionParser;
+input::scan! {
+    #[format("Sensor at x={sx}, y={sy}: closest beacon is at x={bx}, y={by}")]
+    struct Report {
+        sx: i64,
+        sy: i64,
+        bx: i64,
+        by: i64,
+    }
+}
 
 impl FromStr for Position {
     type Err = anyhow::Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut positions = PositionParser::parse(Rule::positions, s)
-            .unwrap()
-            .next()
-            .unwrap()
-            .into_inner();
-        let mut sensor = positions.next().unwrap().into_inner();
-        let sensor = Pos(
-            sensor.next().unwrap().as_str().parse().unwrap(),
-            sensor.next().unwrap().as_str().parse().unwrap(),
-        );
-        let mut beacon = positions.next().unwrap().into_inner();
-        let beacon = Pos(
-            beacon.next().unwrap().as_str().parse().unwrap(),
-            beacon.next().unwrap().as_str().parse().unwrap(),
-        );
-        Ok(Position { sensor, beacon })
+        let Report { sx, sy, bx, by } = s.parse()?;
+        Ok(Position {
+            sensor: Pos(sx, sy),
+            beacon: Pos(bx, by),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_position() {
+        let p: Position =
+            "Sensor at x=2, y=18: closest beacon is at x=-2, y=15"
+                .parse()
+                .unwrap();
+        assert_eq!((p.sensor, p.beacon), (Pos(2, 18), Pos(-2, 15)));
+        assert!("Sensor at x=2, y=18".parse::<Position>().is_err());
     }
 }
@@ -3,8 +3,7 @@ use std::{
     str::FromStr,
 };
 
-use pest::Parser;
-use pest_derive::Parser;
+use anyhow::{bail, Context};
 
 //const INPUT: &str = "input/day16_test.txt";
 const INPUT: &str = "input/day16.txt";
@@ -97,42 +96,38 @@ impl Cave {
     }
 }
 
-#[derive(Parser)]
-#[grammar_inline = r#"
-valve  = { ASCII_ALPHA_UPPER{2} }
-num    = { ASCII_DIGIT+ }
-valves = { valve ~ (", " ~ valve)* }
-rule   = { "Valve " ~ valve ~ " has flow rate=" ~ num ~ ("; tunnel leads to valve " | "; tunnels lead to valves ") ~ valves }
-rules  = { rule ~ ("\n" ~ rule)* }
-"#]
-struct ValveParser;
+impl FromStr for Label {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.chars().collect::<Vec<_>>()[..] {
+            [a, b] if a.is_ascii_uppercase() && b.is_ascii_uppercase() => {
+                Ok(Label(a, b))
+            }
+            _ => bail!("{:?} isn't a valve label", s),
+        }
+    }
+}
+
+input::scan! {
+    // The puzzle says "tunnel leads to valve" when there's only one.
+    #[format("Valve {label} has flow rate={rate}; {_} {_} to {_} {leads_to:, }")]
+    struct Valve {
+        label: Label,
+        rate: u64,
+        leads_to: Vec<Label>,
+    }
+}
 
 impl FromStr for Cave {
-    type Err = ();
+    type Err = anyhow::Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let mut flow_rates = HashMap::new();
         let mut tunnels = HashMap::new();
-        let rules = ValveParser::parse(Rule::rules, s)
-            .unwrap()
-            .next()
-            .unwrap()
-            .into_inner();
-        for rule in rules {
-            let mut rule = rule.into_inner();
-            let mut chars = rule.next().unwrap().as_str().chars();
-            let label = Label(chars.next().unwrap(), chars.next().unwrap());
-            let flow_rate: u64 = rule.next().unwrap().as_str().parse().unwrap();
-            let leads_to = rule
-                .next()
-                .unwrap()
-                .into_inner()
-                .map(|l| {
-                    let mut chars = l.as_str().chars();
-                    Label(chars.next().unwrap(), chars.next().unwrap())
-                })
-                .collect();
-            flow_rates.insert(label, flow_rate);
-            tunnels.insert(label, leads_to);
+        for (i, line) in s.lines().enumerate() {
+            let valve: Valve =
+                line.parse().with_context(|| format!("line {}", i + 1))?;
+            flow_rates.insert(valve.label, valve.rate);
+            tunnels.insert(valve.label, valve.leads_to);
         }
         Ok(Cave {
             flow_rates,
@@ -146,7 +141,17 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_one_plus_one() {
-        assert_eq!(1 + 1, 2);
+    fn test_parse() {
+        let cave: Cave =
+            "Valve AA has flow rate=0; tunnels lead to valves DD, BB
+Valve BB has flow rate=13; tunnel leads to valve AA
+Valve DD has flow rate=20; tunnels lead to valves AA, BB"
+                .parse()
+                .unwrap();
+        assert_eq!(cave.flow_rate(&Label('D', 'D')), 20);
+        assert_eq!(cave.tunnels[&Label('B', 'B')], vec![Label('A', 'A')]);
+        assert!("Valve A has flow rate=0; tunnel leads to valve BB"
+            .parse::<Cave>()
+            .is_err());
     }
 }
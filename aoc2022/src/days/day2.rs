@@ -1,120 +1,314 @@
 use std::str::FromStr;
 
-use anyhow::bail;
+use anyhow::{anyhow, bail, Context};
 
 const INPUT: &str = "input/day2.txt";
 
 pub fn solution() {
     let rounds: Vec<Round> = input::parse_lines(INPUT);
-    let mut part1 = 0;
-    let mut part2 = 0;
-    for r in rounds {
-        part1 += r.part1_score();
-        part2 += r.part2_score();
-    }
+    let game = if crate::options().iter().any(|o| o == "spock") {
+        Game::rock_paper_scissors_lizard_spock()
+    } else {
+        Game::rock_paper_scissors()
+    };
+    let part1 = game.play(&rounds, &AsShape).unwrap();
+    let part2 = game.play(&rounds, &AsOutcome).unwrap();
     println!("part1: {}\npart2: {}", part1, part2);
+
+    let opponents: Vec<Shape> = rounds
+        .iter()
+        .map(|r| game.shape(r.opponent))
+        .collect::<anyhow::Result<_>>()
+        .unwrap();
+    let (_, best) = game.optimal(&opponents);
+    println!("best possible: {}", best);
 }
 
-#[derive(Debug, Copy, Clone)]
-enum Shape {
-    Rock,
-    Paper,
-    Scissors,
+/// An index into a game's shapes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Shape(usize);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Outcome {
+    Lose,
+    Draw,
+    Win,
 }
 
-impl Shape {
-    fn score(&self) -> u64 {
-        match self {
-            Self::Rock => 1,
-            Self::Paper => 2,
-            Self::Scissors => 3,
+/// A game where the shapes stand in a circle and each one beats the shapes
+/// an odd number of steps behind it. With three shapes that's just "shape
+/// i beats shape i-1"; with five it's Rock-Paper-Scissors-Lizard-Spock.
+#[derive(Debug)]
+struct Game {
+    names: Vec<&'static str>,
+    shape_scores: Vec<u64>,
+    /// Indexed by `Outcome as usize`.
+    outcome_scores: [u64; 3],
+}
+
+impl Game {
+    fn new(
+        names: Vec<&'static str>,
+        shape_scores: Vec<u64>,
+        outcome_scores: [u64; 3],
+    ) -> anyhow::Result<Game> {
+        if names.len().is_multiple_of(2) {
+            bail!(
+                "{} shapes would leave some pairs without a winner",
+                names.len()
+            );
+        }
+        if shape_scores.len() != names.len() {
+            bail!(
+                "{} shape scores for {} shapes",
+                shape_scores.len(),
+                names.len()
+            );
         }
+        Ok(Game {
+            names,
+            shape_scores,
+            outcome_scores,
+        })
     }
 
-    fn vs(&self, opponent: Shape) -> Outcome {
-        use Shape::*;
-        match (self, &opponent) {
-            (Rock, Scissors) | (Scissors, Paper) | (Paper, Rock) => {
-                Outcome::Win
-            }
-            (Rock, Rock) | (Paper, Paper) | (Scissors, Scissors) => {
-                Outcome::Draw
-            }
+    fn rock_paper_scissors() -> Game {
+        Game::new(vec!["Rock", "Paper", "Scissors"], vec![1, 2, 3], [0, 3, 6])
+            .unwrap()
+    }
+
+    fn rock_paper_scissors_lizard_spock() -> Game {
+        let names = vec!["Rock", "Paper", "Scissors", "Spock", "Lizard"];
+        Game::new(names, vec![1, 2, 3, 4, 5], [0, 3, 6]).unwrap()
+    }
+
+    fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    fn shape(&self, i: usize) -> anyhow::Result<Shape> {
+        if i >= self.len() {
+            bail!("there are only {} shapes", self.len());
+        }
+        Ok(Shape(i))
+    }
+
+    fn shapes(&self) -> impl Iterator<Item = Shape> {
+        (0..self.len()).map(Shape)
+    }
+
+    fn outcome(&self, you: Shape, opponent: Shape) -> Outcome {
+        match (you.0 + self.len() - opponent.0) % self.len() {
+            0 => Outcome::Draw,
+            steps if steps % 2 == 1 => Outcome::Win,
             _ => Outcome::Lose,
         }
     }
+
+    fn score(&self, you: Shape, opponent: Shape) -> u64 {
+        let outcome = self.outcome(you, opponent);
+        self.outcome_scores[outcome as usize] + self.shape_scores[you.0]
+    }
+
+    /// The highest scoring shape that gets `outcome` against `opponent`.
+    fn shape_for(&self, opponent: Shape, outcome: Outcome) -> Shape {
+        self.shapes()
+            .filter(|&you| self.outcome(you, opponent) == outcome)
+            .max_by_key(|&you| self.score(you, opponent))
+            .expect("every outcome is possible with an odd number of shapes")
+    }
+
+    /// The highest scoring reply to each opponent shape, and the total.
+    fn optimal(&self, opponents: &[Shape]) -> (Vec<Shape>, u64) {
+        let replies: Vec<Shape> = opponents
+            .iter()
+            .map(|&opponent| {
+                self.shapes()
+                    .max_by_key(|&you| self.score(you, opponent))
+                    .unwrap()
+            })
+            .collect();
+        let total = replies
+            .iter()
+            .zip(opponents)
+            .map(|(&you, &opponent)| self.score(you, opponent))
+            .sum();
+        (replies, total)
+    }
+
+    /// Your total score if you follow the strategy guide as `decoder`
+    /// reads it.
+    fn play(
+        &self,
+        rounds: &[Round],
+        decoder: &dyn Decoder,
+    ) -> anyhow::Result<u64> {
+        rounds
+            .iter()
+            .enumerate()
+            .map(|(i, round)| {
+                self.round_score(round, decoder)
+                    .with_context(|| format!("round {}", i + 1))
+            })
+            .sum()
+    }
+
+    fn round_score(
+        &self,
+        round: &Round,
+        decoder: &dyn Decoder,
+    ) -> anyhow::Result<u64> {
+        let opponent = self.shape(round.opponent)?;
+        let you = decoder.decode(self, opponent, round.column)?;
+        Ok(self.score(you, opponent))
+    }
 }
 
-#[derive(Debug, PartialEq, Eq)]
-enum Outcome {
-    Lose,
-    Draw,
-    Win,
+/// A reading of the strategy guide's second column.
+trait Decoder {
+    fn decode(
+        &self,
+        game: &Game,
+        opponent: Shape,
+        column: usize,
+    ) -> anyhow::Result<Shape>;
 }
 
-impl Outcome {
-    fn score(&self) -> u64 {
-        match self {
-            Self::Lose => 0,
-            Self::Draw => 3,
-            Self::Win => 6,
-        }
+/// X, Y, Z, ... name the shape to play.
+struct AsShape;
+
+impl Decoder for AsShape {
+    fn decode(
+        &self,
+        game: &Game,
+        _opponent: Shape,
+        column: usize,
+    ) -> anyhow::Result<Shape> {
+        game.shape(column)
     }
 }
 
-#[derive(Debug)]
-struct Round {
-    opponent: Shape,
-    you: char,
-}
+/// X, Y and Z say whether to lose, draw or win.
+struct AsOutcome;
 
-impl Round {
-    fn part1_score(&self) -> u64 {
-        let you = match self.you {
-            'X' => Shape::Rock,
-            'Y' => Shape::Paper,
-            'Z' => Shape::Scissors,
-            _ => unreachable!(),
-        };
-        let outcome = you.vs(self.opponent);
-        outcome.score() + you.score()
-    }
-
-    fn part2_score(&self) -> u64 {
-        use Outcome::*;
-        use Shape::*;
-        let (outcome, you) = match (self.you, self.opponent) {
-            // X means you need to lose,
-            ('X', Rock) => (Lose, Scissors),
-            ('X', Paper) => (Lose, Rock),
-            ('X', Scissors) => (Lose, Paper),
-            // Y means you need to end the round in a draw,
-            ('Y', Rock) => (Draw, Rock),
-            ('Y', Paper) => (Draw, Paper),
-            ('Y', Scissors) => (Draw, Scissors),
-            // and Z means you need to win.
-            ('Z', Rock) => (Win, Paper),
-            ('Z', Paper) => (Win, Scissors),
-            ('Z', Scissors) => (Win, Rock),
-            _ => unreachable!(),
+impl Decoder for AsOutcome {
+    fn decode(
+        &self,
+        game: &Game,
+        opponent: Shape,
+        column: usize,
+    ) -> anyhow::Result<Shape> {
+        let outcome = match column {
+            0 => Outcome::Lose,
+            1 => Outcome::Draw,
+            2 => Outcome::Win,
+            _ => bail!("column {} isn't an outcome", column),
         };
-        //assert_eq!(outcome, you.vs(self.opponent));
-        outcome.score() + you.score()
+        Ok(game.shape_for(opponent, outcome))
     }
 }
 
+/// A line of the strategy guide, with both columns as offsets from the
+/// first letter each one uses (A and X).
+#[derive(Debug)]
+struct Round {
+    opponent: usize,
+    column: usize,
+}
+
 impl FromStr for Round {
     type Err = anyhow::Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let round = input::space_str::<char>(s);
+        let offset = |c: char, first: char| {
+            (c as u32)
+                .checked_sub(first as u32)
+                .filter(|_| c.is_ascii_uppercase())
+                .map(|n| n as usize)
+                .ok_or_else(|| {
+                    anyhow!("{:?} doesn't come after {:?}", c, first)
+                })
+        };
+        let letter = |column: &str| {
+            let mut chars = column.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Ok(c),
+                _ => Err(anyhow!("{:?} isn't a single letter", column)),
+            }
+        };
+        let columns: Vec<&str> = s.split_ascii_whitespace().collect();
+        let [opponent, you] = columns[..] else {
+            bail!("expected two columns in {:?}", s);
+        };
         Ok(Round {
-            opponent: match round[0] {
-                'A' => Shape::Rock,
-                'B' => Shape::Paper,
-                'C' => Shape::Scissors,
-                _ => bail!("Unknown shape"),
-            },
-            you: round[1],
+            opponent: offset(letter(opponent)?, 'A')?,
+            column: offset(letter(you)?, 'X')?,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "A Y\nB X\nC Z";
+
+    #[test]
+    fn test_play() {
+        let rounds: Vec<Round> = input::parse_str_lines(EXAMPLE);
+        let game = Game::rock_paper_scissors();
+        assert_eq!(game.play(&rounds, &AsShape).unwrap(), 15);
+        assert_eq!(game.play(&rounds, &AsOutcome).unwrap(), 12);
+
+        let rounds: Vec<Round> = input::parse_str_lines("D X");
+        assert!(game.play(&rounds, &AsShape).is_err());
+        assert!("A".parse::<Round>().is_err());
+        assert!("A a".parse::<Round>().is_err());
+        assert!("AB X".parse::<Round>().is_err());
+        assert!("A X Y".parse::<Round>().is_err());
+    }
+
+    #[test]
+    fn test_lizard_spock() {
+        let game = Game::rock_paper_scissors_lizard_spock();
+        let shape =
+            |name| Shape(game.names.iter().position(|n| *n == name).unwrap());
+        for (winner, loser) in [
+            ("Scissors", "Paper"),
+            ("Paper", "Rock"),
+            ("Rock", "Lizard"),
+            ("Lizard", "Spock"),
+            ("Spock", "Scissors"),
+            ("Scissors", "Lizard"),
+            ("Lizard", "Paper"),
+            ("Paper", "Spock"),
+            ("Spock", "Rock"),
+            ("Rock", "Scissors"),
+        ] {
+            assert_eq!(game.outcome(shape(winner), shape(loser)), Outcome::Win);
+            assert_eq!(
+                game.outcome(shape(loser), shape(winner)),
+                Outcome::Lose
+            );
+        }
+        // Rock and Spock both beat Scissors; Spock scores more.
+        let scissors = shape("Scissors");
+        assert_eq!(game.shape_for(scissors, Outcome::Win), shape("Spock"));
+        assert!(Game::new(vec!["A", "B"], vec![1, 2], [0, 3, 6]).is_err());
+    }
+
+    #[test]
+    fn test_optimal() {
+        let game = Game::rock_paper_scissors();
+        let (replies, total) = game.optimal(&[Shape(0), Shape(1), Shape(2)]);
+        // Winning each round beats drawing with the higher scoring shape.
+        assert_eq!(replies, vec![Shape(1), Shape(2), Shape(0)]);
+        assert_eq!(total, 8 + 9 + 7);
+
+        // Once Scissors is worth enough, it's best even when it loses.
+        let game =
+            Game::new(vec!["R", "P", "S"], vec![1, 2, 10], [0, 3, 6]).unwrap();
+        let (replies, total) = game.optimal(&[Shape(0), Shape(2)]);
+        assert_eq!(replies, vec![Shape(2), Shape(2)]);
+        assert_eq!(total, 10 + 13);
+    }
+}
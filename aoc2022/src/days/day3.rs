@@ -1,48 +1,125 @@
-use std::collections::HashSet;
+use std::{ops::BitAnd, str::FromStr};
+
+use anyhow::{bail, Context};
 
 const INPUT: &str = "input/day3.txt";
+const GROUP_SIZE: usize = 3;
 
 pub fn solution() {
     let packs = input::string(INPUT);
-    println!("Part1: {}\nPart2: {}", part1(&packs), part2(&packs));
+    let part1 = part1(&packs).unwrap();
+    let part2 = part2(&packs, GROUP_SIZE).unwrap();
+    println!("Part1: {}\nPart2: {}", part1, part2);
 }
 
-fn part1(input: &str) -> u32 {
+/// Sums the priorities of the items in both compartments of each pack.
+fn part1(input: &str) -> anyhow::Result<u32> {
     let mut sum = 0;
-    for l in input.lines() {
-        let (one, two) = l.split_at(l.len() / 2);
-        let one = one.chars().collect::<HashSet<_>>();
-        for c in two.chars() {
-            if one.contains(&c) {
-                sum += priority(c);
-                break;
-            }
-        }
+    for (i, l) in input.lines().enumerate() {
+        let shared =
+            compartments(l).with_context(|| format!("line {}", i + 1))?;
+        sum += shared.priority_sum();
     }
-    sum
+    Ok(sum)
 }
 
-fn part2(input: &str) -> u32 {
-    let mut sum = 0;
-    for l in input.lines().collect::<Vec<_>>().chunks_exact(3) {
-        let (one, two, three) = (l[0], l[1], l[2]);
-        let one = one.chars().collect::<HashSet<_>>();
-        let two = two.chars().collect::<HashSet<_>>();
-        for c in three.chars() {
-            if one.contains(&c) && two.contains(&c) {
-                sum += priority(c);
-                break;
-            }
+fn compartments(pack: &str) -> anyhow::Result<ItemSet> {
+    // Parsing the whole pack first means it's all ASCII, so the halves
+    // can be split by bytes.
+    pack.parse::<ItemSet>()?;
+    if !pack.len().is_multiple_of(2) {
+        bail!("{:?} can't be split into two compartments", pack);
+    }
+    let (one, two) = pack.split_at(pack.len() / 2);
+    Ok(one.parse::<ItemSet>()? & two.parse()?)
+}
+
+/// Sums the priorities of the items every pack in each group of
+/// `group_size` lines has in common.
+fn part2(input: &str, group_size: usize) -> anyhow::Result<u32> {
+    let packs = input
+        .lines()
+        .enumerate()
+        .map(|(i, l)| l.parse().with_context(|| format!("line {}", i + 1)))
+        .collect::<anyhow::Result<Vec<ItemSet>>>()?;
+    if group_size == 0 || packs.len() % group_size != 0 {
+        bail!(
+            "{} packs don't split into groups of {}",
+            packs.len(),
+            group_size
+        );
+    }
+    Ok(packs
+        .chunks(group_size)
+        .map(|group| {
+            ItemSet::intersection(group.iter().copied()).priority_sum()
+        })
+        .sum())
+}
+
+/// A set of items, as a bitset indexed by priority.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+struct ItemSet(u64);
+
+impl ItemSet {
+    /// Every item, which is what intersecting no sets at all gives.
+    const ALL: ItemSet = ItemSet(((1 << 52) - 1) << 1);
+
+    fn intersection(sets: impl IntoIterator<Item = ItemSet>) -> ItemSet {
+        sets.into_iter().fold(ItemSet::ALL, |a, b| a & b)
+    }
+
+    fn insert(&mut self, item: char) -> anyhow::Result<()> {
+        self.0 |= 1 << priority(item)?;
+        Ok(())
+    }
+
+    fn contains(&self, item: char) -> bool {
+        priority(item).is_ok_and(|p| self.0 & (1 << p) != 0)
+    }
+
+    /// The items in priority order.
+    fn iter(&self) -> impl Iterator<Item = char> + '_ {
+        (1..=52).map(item).filter(|&c| self.contains(c))
+    }
+
+    fn priority_sum(&self) -> u32 {
+        self.iter().map(|c| priority(c).unwrap()).sum()
+    }
+}
+
+impl BitAnd for ItemSet {
+    type Output = ItemSet;
+    fn bitand(self, rhs: Self) -> Self::Output {
+        ItemSet(self.0 & rhs.0)
+    }
+}
+
+impl FromStr for ItemSet {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut set = ItemSet::default();
+        for (i, c) in s.chars().enumerate() {
+            set.insert(c).with_context(|| format!("column {}", i + 1))?;
         }
+        Ok(set)
     }
-    sum
 }
 
-fn priority(c: char) -> u32 {
+fn priority(c: char) -> anyhow::Result<u32> {
     match c {
-        'a'..='z' => c as u32 - 'a' as u32 + 1,
-        'A'..='Z' => c as u32 - 'A' as u32 + 27,
-        _ => panic!("bad char"),
+        'a'..='z' => Ok(c as u32 - 'a' as u32 + 1),
+        'A'..='Z' => Ok(c as u32 - 'A' as u32 + 27),
+        _ => bail!("{:?} isn't an item", c),
+    }
+}
+
+/// The item with priority `p`, which must be in 1..=52.
+fn item(p: u32) -> char {
+    match p {
+        1..=26 => char::from(b'a' + (p - 1) as u8),
+        27..=52 => char::from(b'A' + (p - 27) as u8),
+        _ => panic!("no item has priority {}", p),
     }
 }
 
@@ -50,23 +127,48 @@ fn priority(c: char) -> u32 {
 mod tests {
     use super::*;
 
+    const PACKS: &str = r#"vJrwpWtwJgWrhcsFMMfFFhFp
+jqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL
+PmmdzqPrVvPwwTWBwg
+wMqvLMZHhHMvwLHjbvcjnnSBnvTQFn
+ttgJtRGJQctTZtZT
+CrZsJsPPZsGzwwsLwLmpwMDw"#;
+
     #[test]
     fn test_priority() {
-        assert_eq!(priority('a'), 1);
-        assert_eq!(priority('z'), 26);
-        assert_eq!(priority('A'), 27);
-        assert_eq!(priority('Z'), 52);
+        assert_eq!(priority('a').unwrap(), 1);
+        assert_eq!(priority('z').unwrap(), 26);
+        assert_eq!(priority('A').unwrap(), 27);
+        assert_eq!(priority('Z').unwrap(), 52);
+        assert!(priority('1').is_err());
+        for p in 1..=52 {
+            assert_eq!(priority(item(p)).unwrap(), p);
+        }
     }
 
     #[test]
     fn test_part1_part2() {
-        let packs = r#"vJrwpWtwJgWrhcsFMMfFFhFp
-jqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL
-PmmdzqPrVvPwwTWBwg
-wMqvLMZHhHMvwLHjbvcjnnSBnvTQFn
-ttgJtRGJQctTZtZT
-CrZsJsPPZsGzwwsLwLmpwMDw"#;
-        assert_eq!(part1(packs), 157);
-        assert_eq!(part2(packs), 70);
+        assert_eq!(part1(PACKS).unwrap(), 157);
+        assert_eq!(part2(PACKS, 3).unwrap(), 70);
+        assert_eq!(part2("ab\nbc\nbD\nDe", 2).unwrap(), 2 + 30);
+        assert_eq!(part2("ab\nbc\nbD\nbe", 4).unwrap(), 2);
+        assert!(part2(PACKS, 4).is_err());
+        assert!(part1("abc").is_err());
+        assert!(part1("ab\nc-").is_err());
+        let err = part1("aéb").unwrap_err();
+        assert_eq!(format!("{:#}", err), "line 1: column 2: 'é' isn't an item");
+    }
+
+    #[test]
+    fn test_item_set() {
+        let set: ItemSet = "zaAzZ".parse().unwrap();
+        assert_eq!(set.iter().count(), 4);
+        assert_eq!(set.iter().collect::<String>(), "azAZ");
+        let sets = ["abc", "bcd", "cde"].map(|s| s.parse().unwrap());
+        assert_eq!(ItemSet::intersection(sets).iter().collect::<String>(), "c");
+        assert_eq!(ItemSet::intersection([]), ItemSet::ALL);
+        assert_eq!(ItemSet::ALL.iter().count(), 52);
+        let err = "ab c".parse::<ItemSet>().unwrap_err();
+        assert_eq!(format!("{:#}", err), "column 3: ' ' isn't an item");
     }
 }
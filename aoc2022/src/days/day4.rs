@@ -1,57 +1,157 @@
-use std::str::FromStr;
+use anyhow::Context;
+use util::interval::{self, Interval};
 
 const INPUT: &str = "input/day4.txt";
 
 pub fn solution() {
-    let assignments: Vec<Assignment> = input::parse_lines(INPUT);
+    let assignments = parse_assignments(&input::string(INPUT)).unwrap();
     let mut part1 = 0;
     let mut part2 = 0;
-    for a in assignments {
-        if a.r1.contains(&a.r2) || a.r2.contains(&a.r1) {
+    for Assignment { first, second } in &assignments {
+        if first.contains(second) || second.contains(first) {
             part1 += 1;
         }
-        if a.r1.overlaps(&a.r2) {
+        if first.overlaps(second) {
             part2 += 1;
         }
     }
     println!("Part1: {}\nPart2: {}", part1, part2);
+
+    let roster = Roster::new(&assignments);
+    println!("Redundant elves: {}", roster.redundant().len());
+    let covered: u64 = roster.covered().iter().map(Interval::len).sum();
+    println!("Sections covered: {}", covered);
+    println!("Most elves on one section: {}", roster.max_overlap());
+    let groups = roster.overlapping_groups();
+    let largest = groups.iter().map(Vec::len).max().unwrap_or(0);
+    println!("Overlapping groups: {} (largest {})", groups.len(), largest);
+}
+
+input::scan! {
+    #[format("{first},{second}")]
+    struct Assignment {
+        first: Interval,
+        second: Interval,
+    }
+}
+
+fn parse_assignments(s: &str) -> anyhow::Result<Vec<Assignment>> {
+    s.lines()
+        .enumerate()
+        .map(|(i, l)| l.parse().with_context(|| format!("line {}", i + 1)))
+        .collect()
 }
 
-struct Assignment {
-    r1: Range,
-    r2: Range,
+/// Identifies an elf by the line of its assignment, counting from 1, and
+/// whether it's first or second on that line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Elf {
+    line: usize,
+    second: bool,
 }
 
-struct Range(u32, u32);
+/// Queries across every elf on the list rather than within pairs.
+struct Roster {
+    sections: Vec<Interval>,
+}
 
-impl Range {
-    fn contains(&self, other: &Range) -> bool {
-        self.0 <= other.0 && self.1 >= other.1
+impl Roster {
+    fn new(assignments: &[Assignment]) -> Roster {
+        let sections = assignments
+            .iter()
+            .flat_map(|a| [a.first, a.second])
+            .collect();
+        Roster { sections }
     }
 
-    fn overlaps(&self, other: &Range) -> bool {
-        (self.0..=self.1).contains(&other.0)
-            || (self.0..=self.1).contains(&other.1)
-            || (other.0..=other.1).contains(&self.0)
-            || (other.0..=other.1).contains(&self.1)
+    fn elf(i: usize) -> Elf {
+        Elf {
+            line: i / 2 + 1,
+            second: i % 2 == 1,
+        }
     }
-}
 
-impl FromStr for Assignment {
-    type Err = ();
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (r1, r2) = s.split_once(',').unwrap();
-        Ok(Assignment {
-            r1: r1.parse().unwrap(),
-            r2: r2.parse().unwrap(),
-        })
+    /// Elves whose sections some other elf already covers. Of elves with
+    /// identical sections, the earliest isn't redundant.
+    fn redundant(&self) -> Vec<Elf> {
+        interval::redundant(&self.sections)
+            .into_iter()
+            .map(Roster::elf)
+            .collect()
+    }
+
+    /// Every section assigned to anyone, as disjoint runs.
+    fn covered(&self) -> Vec<Interval> {
+        interval::union(self.sections.iter().copied())
+    }
+
+    /// The most elves assigned to any one section.
+    fn max_overlap(&self) -> usize {
+        interval::max_overlap(&self.sections)
+    }
+
+    /// Each maximal group of elves that share at least one section.
+    fn overlapping_groups(&self) -> Vec<Vec<Elf>> {
+        interval::overlapping_groups(&self.sections)
+            .into_iter()
+            .map(|group| group.into_iter().map(Roster::elf).collect())
+            .collect()
     }
 }
 
-impl FromStr for Range {
-    type Err = ();
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (begin, end) = s.split_once('-').unwrap();
-        Ok(Range(begin.parse().unwrap(), end.parse().unwrap()))
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "2-4,6-8
+2-3,4-5
+5-7,7-9
+2-8,3-7
+6-6,4-6
+2-6,4-8";
+
+    fn elf(line: usize, second: bool) -> Elf {
+        Elf { line, second }
+    }
+
+    #[test]
+    fn test_roster() {
+        let roster = Roster::new(&parse_assignments(EXAMPLE).unwrap());
+        // Only 7-9 and 2-8 aren't inside someone else's sections.
+        let kept = [elf(3, true), elf(4, false)];
+        let redundant = roster.redundant();
+        assert_eq!(redundant.len(), 10);
+        assert!(kept.iter().all(|e| !redundant.contains(e)));
+        assert_eq!(roster.covered(), vec![Interval::new(2, 9).unwrap()]);
+        assert_eq!(roster.max_overlap(), 8);
+        // The groups peak at sections 3, 4, 5, 6 and 7.
+        let groups = roster.overlapping_groups();
+        assert_eq!(groups.len(), 5);
+        assert_eq!(
+            groups[0],
+            vec![
+                elf(1, false),
+                elf(2, false),
+                elf(4, false),
+                elf(4, true),
+                elf(6, false)
+            ]
+        );
+        assert_eq!(groups[3].len(), 8);
+    }
+
+    #[test]
+    fn test_assignment() {
+        let Assignment { first, second } = "2-4,6-8".parse().unwrap();
+        assert_eq!((first.start(), first.end()), (2, 4));
+        assert_eq!((second.start(), second.end()), (6, 8));
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = parse_assignments("2-4,6-8\n2-3;4-5").err().unwrap();
+        assert_eq!(err.to_string(), "line 2");
+        assert!(parse_assignments("2-4,8-6").is_err());
+        assert!(parse_assignments("2-4,6").is_err());
     }
 }
@@ -1,110 +1,285 @@
-use std::str::FromStr;
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::{anyhow, bail, Context};
 
 const INPUT: &str = "input/day5.txt";
 
 pub fn solution() {
-    let mut input: Input = input::parse(INPUT);
-    let part1 = input.clone().execute();
-    println!("Part1: {}", part1);
-    let part2 = input.execute_9001();
-    println!("Part1: {}", part2);
+    let input: Input = input::parse(INPUT);
+
+    let mut stacks = input.stacks.clone();
+    stacks.run(&CrateMover9000, &input.moves).unwrap();
+    println!("Part1: {}", stacks.top_crates());
+
+    let mut stacks = input.stacks;
+    stacks.run(&CrateMover9001, &input.moves).unwrap();
+    println!("Part2: {}", stacks.top_crates());
+}
+
+/// A way of moving crates between stacks.
+trait Crane {
+    /// Given the crates lifted off the top of a stack, bottom first, returns
+    /// them in the order they're put down.
+    fn arrange(&self, crates: Vec<char>) -> Vec<char>;
+}
+
+/// Moves one crate at a time, so a lifted pile ends up upside down.
+struct CrateMover9000;
+
+impl Crane for CrateMover9000 {
+    fn arrange(&self, mut crates: Vec<char>) -> Vec<char> {
+        crates.reverse();
+        crates
+    }
 }
 
-#[derive(Debug, Default, Clone)]
+/// Moves the whole pile at once, keeping its order.
+struct CrateMover9001;
+
+impl Crane for CrateMover9001 {
+    fn arrange(&self, crates: Vec<char>) -> Vec<char> {
+        crates
+    }
+}
+
+#[derive(Debug, Clone)]
 struct Input {
-    cranes: Vec<Vec<char>>,
-    instructions: Vec<Instruction>,
+    stacks: Stacks,
+    moves: Vec<Move>,
 }
 
-impl Input {
-    fn execute(&mut self) -> String {
-        for &Instruction { count, from, to } in &self.instructions {
-            for _ in 0..count {
-                let item = self.cranes[from].pop().unwrap();
-                self.cranes[to].push(item);
-            }
+/// Stacks of crates, bottom first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Stacks(Vec<Vec<char>>);
+
+impl Stacks {
+    /// Carries out one move. A move that would need more crates than the
+    /// stack holds, or that names a stack that doesn't exist, leaves every
+    /// stack untouched.
+    fn apply(&mut self, crane: &dyn Crane, mv: &Move) -> anyhow::Result<()> {
+        let &Move { count, from, to } = mv;
+        if from >= self.0.len() || to >= self.0.len() {
+            bail!("there are only {} stacks", self.0.len());
         }
-        self.top_crates()
+        let stack = &mut self.0[from];
+        let Some(start) = stack.len().checked_sub(count) else {
+            bail!("stack {} only has {} crates", from + 1, stack.len());
+        };
+        let crates = crane.arrange(stack.split_off(start));
+        self.0[to].extend(crates);
+        Ok(())
     }
 
-    fn execute_9001(&mut self) -> String {
-        for &Instruction { count, from, to } in &self.instructions {
-            let mut items = Vec::with_capacity(count);
-            for _ in 0..count {
-                items.push(self.cranes[from].pop().unwrap());
-            }
-            self.cranes[to].extend(items.into_iter().rev());
+    /// Carries out the moves in order, stopping at the first that fails.
+    fn run(&mut self, crane: &dyn Crane, moves: &[Move]) -> anyhow::Result<()> {
+        for (i, mv) in moves.iter().enumerate() {
+            self.apply(crane, mv)
+                .with_context(|| format!("move {} ({})", i + 1, mv))?;
         }
-        self.top_crates()
+        Ok(())
     }
 
+    /// The crate on top of each non-empty stack.
     fn top_crates(&self) -> String {
-        self.cranes.iter().map(|c| c.last().unwrap()).collect()
+        self.0.iter().filter_map(|s| s.last()).collect()
+    }
+}
+
+/// Draws the stacks the way the puzzle input does.
+impl Display for Stacks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let height = self.0.iter().map(Vec::len).max().unwrap_or(0);
+        for level in (0..height).rev() {
+            let row: Vec<String> = self
+                .0
+                .iter()
+                .map(|s| match s.get(level) {
+                    Some(c) => format!("[{}]", c),
+                    None => "   ".to_string(),
+                })
+                .collect();
+            writeln!(f, "{}", row.join(" "))?;
+        }
+        let labels: Vec<String> =
+            (1..=self.0.len()).map(|i| format!(" {} ", i)).collect();
+        write!(f, "{}", labels.join(" "))
+    }
+}
+
+/// Reads the drawing, using the positions of the labels on the last line to
+/// find each stack's column.
+impl FromStr for Stacks {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines: Vec<&str> = s.lines().collect();
+        let labels = lines.pop().ok_or_else(|| anyhow!("empty drawing"))?;
+        let columns: Vec<usize> = labels
+            .char_indices()
+            .filter(|(_, c)| !c.is_ascii_whitespace())
+            .map(|(i, _)| i)
+            .collect();
+        let mut stacks = vec![Vec::new(); columns.len()];
+        for (level, line) in lines.iter().rev().enumerate() {
+            let line = line.as_bytes();
+            for (i, (stack, &col)) in
+                stacks.iter_mut().zip(&columns).enumerate()
+            {
+                match line.get(col) {
+                    Some(c) if c.is_ascii_alphabetic() => {
+                        if stack.len() != level {
+                            bail!(
+                                "crate {} in stack {} is floating",
+                                *c as char,
+                                i + 1
+                            );
+                        }
+                        stack.push(*c as char);
+                    }
+                    None | Some(b' ') => {}
+                    Some(c) => bail!("unexpected {:?} in drawing", *c as char),
+                }
+            }
+        }
+        Ok(Stacks(stacks))
     }
 }
 
-#[derive(Debug, Default, Copy, Clone)]
-struct Instruction {
+/// `count`, `from` and `to`, with the stacks numbered from zero.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+struct Move {
     count: usize,
     from: usize,
     to: usize,
 }
 
+impl Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "move {} from {} to {}",
+            self.count,
+            self.from + 1,
+            self.to + 1
+        )
+    }
+}
+
 impl FromStr for Input {
-    type Err = ();
+    type Err = anyhow::Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut drawing = Vec::new();
-        let mut lines = s.lines();
-        for l in lines.by_ref() {
-            if l.is_empty() {
-                break;
-            }
-            let chars: Vec<char> = l.chars().collect();
-            drawing.push(chars)
-        }
+        let s = s.replace("\r\n", "\n");
+        let (drawing, moves) = s
+            .split_once("\n\n")
+            .ok_or_else(|| anyhow!("no blank line after the drawing"))?;
+        let stacks = drawing.parse()?;
+        let moves = moves
+            .lines()
+            .enumerate()
+            .map(|(i, l)| l.parse().with_context(|| format!("line {}", i + 1)))
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Input { stacks, moves })
+    }
+}
 
-        // Allocate the cranes.
-        let num_cranes = drawing
-            .pop()
-            .unwrap()
-            .into_iter()
-            .filter(|c| !c.is_ascii_whitespace())
-            .count();
-        let mut cranes: Vec<Vec<char>> = vec![Vec::new(); num_cranes];
-
-        // And fill them...
-        drawing.reverse();
-        for line in drawing {
-            for (i, item) in line.iter().skip(1).step_by(4).enumerate() {
-                if *item != ' ' {
-                    cranes[i].push(*item);
-                }
-            }
-        }
-        let mut instructions = Vec::new();
-        for l in lines {
-            let inst: Instruction = l.parse().unwrap();
-            instructions.push(inst);
-        }
-        Ok(Input {
-            cranes,
-            instructions,
+impl FromStr for Move {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words: Vec<&str> = s.split_ascii_whitespace().collect();
+        let ["move", count, "from", from, "to", to] = words[..] else {
+            bail!("{:?} isn't a move", s);
+        };
+        let stack = |n: &str| match n.parse::<usize>()? {
+            0 => bail!("stacks are numbered from 1"),
+            n => Ok(n - 1),
+        };
+        Ok(Move {
+            count: count.parse()?,
+            from: stack(from)?,
+            to: stack(to)?,
         })
     }
 }
 
-impl FromStr for Instruction {
-    type Err = ();
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let s = s
-            .strip_prefix("move ")
-            .unwrap()
-            .replace("from ", "")
-            .replace("to ", "");
-        let mut split = s.split_ascii_whitespace();
-        let count = split.next().unwrap().parse().unwrap();
-        let from = split.next().unwrap().parse::<usize>().unwrap() - 1;
-        let to = split.next().unwrap().parse::<usize>().unwrap() - 1;
-        Ok(Instruction { count, from, to })
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str =
+        "    [D]    \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 \n\n\
+        move 1 from 2 to 1\n\
+        move 3 from 1 to 3\n\
+        move 2 from 2 to 1\n\
+        move 1 from 1 to 2";
+
+    #[test]
+    fn test_cranes() {
+        let input: Input = EXAMPLE.parse().unwrap();
+        let mut stacks = input.stacks.clone();
+        stacks.run(&CrateMover9000, &input.moves).unwrap();
+        assert_eq!(stacks.top_crates(), "CMZ");
+
+        let mut stacks = input.stacks;
+        stacks.run(&CrateMover9001, &input.moves).unwrap();
+        assert_eq!(stacks.top_crates(), "MCD");
+    }
+
+    #[test]
+    fn test_custom_crane() {
+        /// Can only carry two crates at a time.
+        struct Pairwise;
+        impl Crane for Pairwise {
+            fn arrange(&self, crates: Vec<char>) -> Vec<char> {
+                let chunks: Vec<_> = crates.rchunks(2).collect();
+                chunks.concat()
+            }
+        }
+        let input: Input = EXAMPLE.parse().unwrap();
+        let mut stacks = input.stacks;
+        stacks.run(&Pairwise, &input.moves).unwrap();
+        assert_eq!(stacks.0[2], vec!['P', 'N', 'D', 'Z']);
+        assert_eq!(stacks.top_crates(), "MCZ");
+    }
+
+    #[test]
+    fn test_render() {
+        let input: Input = EXAMPLE.parse().unwrap();
+        let drawing = EXAMPLE.split("\n\n").next().unwrap();
+        assert_eq!(input.stacks.to_string(), drawing);
+
+        let mut stacks = input.stacks;
+        stacks.apply(&CrateMover9000, &input.moves[0]).unwrap();
+        assert_eq!(
+            stacks.to_string(),
+            "[D]        \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 "
+        );
+        assert_eq!(stacks.to_string().parse::<Stacks>().unwrap(), stacks);
+    }
+
+    #[test]
+    fn test_errors() {
+        let input: Input = EXAMPLE.parse().unwrap();
+        let mut stacks = input.stacks.clone();
+        let moves = [input.moves[0], input.moves[1], input.moves[1]];
+        let err = stacks.run(&CrateMover9001, &moves).unwrap_err();
+        assert_eq!(
+            format!("{:#}", err),
+            "move 3 (move 3 from 1 to 3): stack 1 only has 0 crates"
+        );
+        // The failed move didn't put anything down.
+        assert_eq!(stacks.0[2], vec!['P', 'Z', 'N', 'D']);
+
+        assert!(stacks
+            .apply(
+                &CrateMover9000,
+                &Move {
+                    count: 1,
+                    from: 0,
+                    to: 3
+                }
+            )
+            .is_err());
+        assert!("move 1 from 0 to 1".parse::<Move>().is_err());
+        assert!("move 1 from 2".parse::<Move>().is_err());
+        assert!("[A]    \n    [B]\n 1   2 ".parse::<Stacks>().is_err());
     }
 }
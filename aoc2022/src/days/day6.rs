@@ -1,18 +1,263 @@
-use std::collections::HashSet;
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{self, BufReader, Read},
+    iter::Map,
+};
+
+use anyhow::bail;
 
 const INPUT: &str = "input/day6.txt";
+/// The marker lengths for parts 1 and 2.
+const LENGTHS: [usize; 2] = [4, 14];
 
 pub fn solution() {
-    let input: Vec<char> = input::string(INPUT).chars().collect();
-    let part1 = find_unique(&input, 4);
-    let part2 = find_unique(&input, 14);
-    println!("Part1: {}\nPart2: {}", part1, part2);
-}
-
-fn find_unique(input: &[char], size: usize) -> usize {
-    size + input
-        .windows(size)
-        .map(|window| window.iter().collect::<HashSet<_>>().len())
-        .take_while(|unique| unique != &size)
-        .count()
+    let file = File::open(input::path(INPUT)).unwrap();
+    let detector = MarkerDetector::new(&LENGTHS);
+    let [part1, part2] = first_markers(detector.read(file)).unwrap();
+    println!("Part1: {}\nPart2: {}", part1.unwrap(), part2.unwrap());
+
+    // Any datastreams given to the runner are checked too.
+    for stream in crate::options() {
+        let detector = MarkerDetector::new(&LENGTHS);
+        match first_markers(detector.detect(stream.bytes())) {
+            Ok(first) => println!("{}: {:?}", stream, first),
+            Err(e) => println!("{}: {}", stream, e),
+        }
+    }
+}
+
+/// The offset of the first marker of each of `LENGTHS`, if there is one.
+fn first_markers(
+    markers: impl Iterator<Item = anyhow::Result<Marker>>,
+) -> anyhow::Result<[Option<usize>; 2]> {
+    let mut first = [None, None];
+    for marker in markers {
+        let Marker { length, offset } = marker?;
+        let i = usize::from(length == LENGTHS[1]);
+        first[i] = first[i].or(Some(offset));
+    }
+    Ok(first)
+}
+
+/// The end of a run of `length` distinct letters, counted as the number of
+/// bytes read up to and including its last letter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Marker {
+    length: usize,
+    offset: usize,
+}
+
+/// Letter counts for the last `length` bytes, along with how many letters
+/// appear more than once, so each byte only costs a couple of updates.
+#[derive(Debug, Clone)]
+struct Window {
+    length: usize,
+    counts: [u32; 26],
+    duplicates: usize,
+}
+
+/// Finds markers of several lengths in a single pass over a stream of
+/// lowercase letters.
+#[derive(Debug, Clone)]
+struct MarkerDetector {
+    windows: Vec<Window>,
+    /// Enough of the most recent letters to know what leaves each window.
+    recent: VecDeque<u8>,
+    offset: usize,
+}
+
+impl MarkerDetector {
+    fn new(lengths: &[usize]) -> Self {
+        let windows = lengths
+            .iter()
+            .map(|&length| Window {
+                length,
+                counts: [0; 26],
+                duplicates: 0,
+            })
+            .collect();
+        MarkerDetector {
+            windows,
+            recent: VecDeque::new(),
+            offset: 0,
+        }
+    }
+
+    /// Takes in the next byte, returning the markers that end with it.
+    fn push(&mut self, byte: u8) -> anyhow::Result<Vec<Marker>> {
+        if !byte.is_ascii_lowercase() {
+            bail!(
+                "{:?} at offset {} isn't a letter",
+                byte as char,
+                self.offset
+            );
+        }
+        let letter = (byte - b'a') as usize;
+        let longest = self.windows.iter().map(|w| w.length).max().unwrap_or(0);
+        self.recent.push_back(letter as u8);
+        if self.recent.len() > longest + 1 {
+            self.recent.pop_front();
+        }
+        self.offset += 1;
+
+        let mut markers = Vec::new();
+        for w in &mut self.windows {
+            w.counts[letter] += 1;
+            if w.counts[letter] == 2 {
+                w.duplicates += 1;
+            }
+            if let Some(i) = self.recent.len().checked_sub(w.length + 1) {
+                let gone = self.recent[i] as usize;
+                w.counts[gone] -= 1;
+                if w.counts[gone] == 1 {
+                    w.duplicates -= 1;
+                }
+            }
+            if self.offset >= w.length && w.duplicates == 0 {
+                markers.push(Marker {
+                    length: w.length,
+                    offset: self.offset,
+                });
+            }
+        }
+        Ok(markers)
+    }
+
+    /// Every marker in `bytes`, in order of offset.
+    fn detect<I>(self, bytes: I) -> Markers<Map<I::IntoIter, ReadOk>>
+    where
+        I: IntoIterator<Item = u8>,
+    {
+        Markers {
+            detector: self,
+            bytes: bytes.into_iter().map(Ok),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Every marker read from `reader`, in order of offset.
+    fn read<R: Read>(self, reader: R) -> Markers<io::Bytes<BufReader<R>>> {
+        Markers {
+            detector: self,
+            bytes: BufReader::new(reader).bytes(),
+            pending: VecDeque::new(),
+        }
+    }
+}
+
+/// Lets bytes that are already in memory pass for a reader's.
+type ReadOk = fn(u8) -> io::Result<u8>;
+
+/// The markers found in a stream, which ends at the first line ending,
+/// whether that's `\n` or `\r\n`.
+struct Markers<I> {
+    detector: MarkerDetector,
+    bytes: I,
+    pending: VecDeque<Marker>,
+}
+
+impl<I: Iterator<Item = io::Result<u8>>> Iterator for Markers<I> {
+    type Item = anyhow::Result<Marker>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pending.is_empty() {
+            let markers = self
+                .bytes
+                .next()?
+                .map_err(anyhow::Error::from)
+                .and_then(|b| match b {
+                    b'\n' | b'\r' => Ok(None),
+                    b => self.detector.push(b).map(Some),
+                });
+            match markers {
+                Ok(None) => return None,
+                Ok(Some(markers)) => self.pending.extend(markers),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        self.pending.pop_front().map(Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    const EXAMPLES: [(&str, usize, usize); 4] = [
+        ("mjqjpqmgbljsphdztnvjfqwrcgsmlb", 7, 19),
+        ("bvwbjplbgvbhsrlpgdmjqwftvncz", 5, 23),
+        ("nppdvjthqldpwncqszvftbrmjlhg", 6, 23),
+        ("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg", 10, 29),
+    ];
+
+    fn first(s: &str, length: usize) -> usize {
+        MarkerDetector::new(&[length])
+            .read(s.as_bytes())
+            .next()
+            .unwrap()
+            .unwrap()
+            .offset
+    }
+
+    #[test]
+    fn test_first() {
+        for (s, part1, part2) in EXAMPLES {
+            assert_eq!(first(s, 4), part1, "{}", s);
+            assert_eq!(first(s, 14), part2, "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_first_markers() {
+        for (s, part1, part2) in EXAMPLES {
+            let markers = MarkerDetector::new(&LENGTHS).detect(s.bytes());
+            let first = first_markers(markers).unwrap();
+            assert_eq!(first, [Some(part1), Some(part2)], "{}", s);
+        }
+        let markers = MarkerDetector::new(&LENGTHS).detect(*b"abcd");
+        assert_eq!(first_markers(markers).unwrap(), [Some(4), None]);
+    }
+
+    #[test]
+    fn test_all_offsets() {
+        // Every marker should match a window that really is distinct.
+        for (s, _, _) in EXAMPLES {
+            let lengths = [1, 3, 4, 14];
+            let found: Vec<_> = MarkerDetector::new(&lengths)
+                .detect(s.bytes())
+                .map(Result::unwrap)
+                .collect();
+            let mut expected = Vec::new();
+            for offset in 1..=s.len() {
+                for length in lengths {
+                    let Some(start) = offset.checked_sub(length) else {
+                        continue;
+                    };
+                    let window = &s.as_bytes()[start..offset];
+                    if window.iter().collect::<HashSet<_>>().len() == length {
+                        expected.push(Marker { length, offset });
+                    }
+                }
+            }
+            assert_eq!(found, expected, "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut markers = MarkerDetector::new(&[2]).detect(*b"abC");
+        assert_eq!(markers.next().unwrap().unwrap().offset, 2);
+        assert!(markers.next().unwrap().is_err());
+
+        let mut markers = MarkerDetector::new(&[2]).read(&b"ab\nC"[..]);
+        assert_eq!(markers.next().unwrap().unwrap().offset, 2);
+        assert!(markers.next().is_none());
+
+        let mut markers = MarkerDetector::new(&[2]).read(&b"ab\r\n"[..]);
+        assert_eq!(markers.next().unwrap().unwrap().offset, 2);
+        assert!(markers.next().is_none());
+    }
 }
@@ -1,110 +1,263 @@
-use std::{collections::HashMap, str::FromStr};
+use std::{collections::BTreeMap, fmt::Display, str::FromStr};
+
+use anyhow::{anyhow, bail, Context};
 
 const INPUT: &str = "input/day7.txt";
 
+const DISK: Disk = Disk {
+    total: 70000000,
+    required: 30000000,
+};
+
 pub fn solution() {
     let terminal: Vec<Terminal> = input::parse_lines(INPUT);
+    let fs = FsTree::replay(&terminal).unwrap();
+    let sizes = fs.sizes();
 
-    // First, build the filesystem in a HashMap.
-    let mut fs: HashMap<String, Vec<File>> = HashMap::new();
-    {
-        let mut cwd = Path::default();
-        for t in terminal {
-            match t {
-                Terminal::Cd(dir) => {
-                    cwd.push(dir.clone());
-                }
-                Terminal::Ls => (), // Ignored.
-                Terminal::File(File::Dir(dir)) => {
-                    fs.entry(cwd.path())
-                        .or_default()
-                        .push(File::Dir(cwd.join(dir).path()));
-                }
-                Terminal::File(File::File(file, size)) => {
-                    fs.entry(cwd.path())
-                        .or_default()
-                        .push(File::File(file, size));
-                }
-            }
+    let part1: u64 = fs
+        .dirs()
+        .map(|id| sizes[id])
+        .filter(|size| *size <= 100000)
+        .sum();
+    println!("Part1: {}", part1);
+
+    let part2 = fs.smallest_to_free(DISK).unwrap();
+    println!("Part2: {}", part2);
+
+    if crate::options().iter().any(|o| o == "du") {
+        print!("{}", fs.du());
+    }
+}
+
+/// The size of a disk and how much of it an update needs free.
+#[derive(Debug, Clone, Copy)]
+struct Disk {
+    total: u64,
+    required: u64,
+}
+
+type NodeId = usize;
+
+#[derive(Debug)]
+struct Node {
+    name: String,
+    parent: Option<NodeId>,
+    kind: Kind,
+}
+
+#[derive(Debug)]
+enum Kind {
+    Dir {
+        children: BTreeMap<String, NodeId>,
+        listed: bool,
+    },
+    File(u64),
+}
+
+/// A filesystem rebuilt from a terminal transcript. Node 0 is the root.
+#[derive(Debug)]
+struct FsTree {
+    nodes: Vec<Node>,
+}
+
+impl FsTree {
+    const ROOT: NodeId = 0;
+
+    fn new() -> Self {
+        FsTree {
+            nodes: vec![Node {
+                name: "/".to_owned(),
+                parent: None,
+                kind: Kind::Dir {
+                    children: BTreeMap::new(),
+                    listed: false,
+                },
+            }],
+        }
+    }
+
+    /// Replays a transcript. Changing into a directory that hasn't been
+    /// listed, listing a directory twice, listing the same name twice, or
+    /// output that doesn't follow an `ls` are all errors, reported with
+    /// their line number.
+    fn replay(terminal: &[Terminal]) -> anyhow::Result<Self> {
+        let mut fs = FsTree::new();
+        let mut cwd = Self::ROOT;
+        let mut listing = false;
+        for (i, t) in terminal.iter().enumerate() {
+            fs.replay_line(t, &mut cwd, &mut listing)
+                .with_context(|| format!("line {}: {}", i + 1, t))?;
         }
+        Ok(fs)
     }
 
-    // Now build a map of directory -> size.
-    let mut sizes: HashMap<&str, u64> = HashMap::new();
-    {
-        let mut stack = vec!["/"];
-        while let Some(path) = stack.pop() {
-            let listing = fs.get(path).unwrap();
-            // Try to get the size of this directory using the sizes we have so far.
-            let subs: Option<Vec<u64>> = listing
-                .iter()
-                .map(|f| match f {
-                    File::Dir(dir) => sizes.get(dir.as_str()).copied(),
-                    File::File(_, size) => Some(*size),
-                })
-                .collect();
-            match subs {
-                // We were successful! Add this directory to the map.
-                Some(subs) => {
-                    sizes.insert(path, subs.iter().sum());
+    fn replay_line(
+        &mut self,
+        t: &Terminal,
+        cwd: &mut NodeId,
+        listing: &mut bool,
+    ) -> anyhow::Result<()> {
+        if let Terminal::Cd(_) = t {
+            *listing = false;
+        }
+        match t {
+            Terminal::Cd(dir) if dir == "/" => *cwd = Self::ROOT,
+            Terminal::Cd(dir) if dir == ".." => {
+                *cwd = self.nodes[*cwd]
+                    .parent
+                    .ok_or_else(|| anyhow!("the root has no parent"))?;
+            }
+            Terminal::Cd(dir) => match self.child(*cwd, dir) {
+                Some(id) if matches!(self.nodes[id].kind, Kind::Dir { .. }) => {
+                    *cwd = id
                 }
-                // We don't have enough info. Re-add this path to the stack
-                // and then push sub-directories so we process them first.
-                None => {
-                    stack.push(path);
-                    for l in listing.iter() {
-                        if let File::Dir(dir) = l {
-                            stack.push(dir);
-                        }
-                    }
+                Some(_) => bail!("{} is a file", dir),
+                None => bail!("{} hasn't been listed", dir),
+            },
+            Terminal::Ls => {
+                let Kind::Dir { listed, .. } = &mut self.nodes[*cwd].kind
+                else {
+                    unreachable!("cwd is always a directory");
+                };
+                if *listed {
+                    bail!("{} was already listed", self.path(*cwd));
                 }
+                *listed = true;
+                *listing = true;
+            }
+            Terminal::Entry(_) if !*listing => bail!("output without ls"),
+            Terminal::Entry(Entry::Dir(name)) => self.add(
+                *cwd,
+                name,
+                Kind::Dir {
+                    children: BTreeMap::new(),
+                    listed: false,
+                },
+            )?,
+            Terminal::Entry(Entry::File(name, size)) => {
+                self.add(*cwd, name, Kind::File(*size))?
             }
         }
+        Ok(())
     }
 
-    let part1: u64 = sizes.values().copied().filter(|f| f <= &100000).sum();
-    println!("Part1: {}", part1);
+    fn add(
+        &mut self,
+        parent: NodeId,
+        name: &str,
+        kind: Kind,
+    ) -> anyhow::Result<()> {
+        if self.child(parent, name).is_some() {
+            bail!("{} is already in {}", name, self.path(parent));
+        }
+        let id = self.nodes.len();
+        self.nodes.push(Node {
+            name: name.to_owned(),
+            parent: Some(parent),
+            kind,
+        });
+        if let Kind::Dir { children, .. } = &mut self.nodes[parent].kind {
+            children.insert(name.to_owned(), id);
+        }
+        Ok(())
+    }
 
-    let available = 70000000 - sizes.get("/").unwrap();
-    let need = 30000000 - available;
-    let part2: u64 = sizes
-        .values()
-        .copied()
-        .filter(|f| f >= &need)
-        .min()
-        .unwrap();
-    println!("Part2: {}", part2);
-}
+    fn child(&self, dir: NodeId, name: &str) -> Option<NodeId> {
+        match &self.nodes[dir].kind {
+            Kind::Dir { children, .. } => children.get(name).copied(),
+            Kind::File(_) => None,
+        }
+    }
+
+    fn children(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        let children = match &self.nodes[id].kind {
+            Kind::Dir { children, .. } => Some(children.values().copied()),
+            Kind::File(_) => None,
+        };
+        children.into_iter().flatten()
+    }
 
-#[derive(Debug, Default, Clone)]
-struct Path(Vec<String>);
+    fn dirs(&self) -> impl Iterator<Item = NodeId> + '_ {
+        (0..self.nodes.len())
+            .filter(|&id| matches!(self.nodes[id].kind, Kind::Dir { .. }))
+    }
 
-impl Path {
-    /// Both push and join assume a single path segment.
-    /// I.e. push("foo/bar") is unsupported.
-    fn push(&mut self, dir: String) {
-        if dir == ".." {
-            self.0.pop().expect("path is empty!");
-            return;
+    fn path(&self, id: NodeId) -> String {
+        let mut names = Vec::new();
+        let mut node = Some(id);
+        while let Some(id) = node.filter(|&id| id != Self::ROOT) {
+            names.push(self.nodes[id].name.as_str());
+            node = self.nodes[id].parent;
         }
-        if let Some(dir) = dir.strip_prefix('/') {
-            self.0.clear();
-            if !dir.is_empty() {
-                self.0.push(dir.to_owned());
+        names.reverse();
+        format!("/{}", names.join("/"))
+    }
+
+    /// The size of every node, indexed by id. Children are always added
+    /// after their parents, so walking backwards sees them first.
+    fn sizes(&self) -> Vec<u64> {
+        let mut sizes = vec![0; self.nodes.len()];
+        for (id, node) in self.nodes.iter().enumerate().rev() {
+            if let Kind::File(size) = node.kind {
+                sizes[id] = size;
+            }
+            if let Some(parent) = node.parent {
+                sizes[parent] += sizes[id];
             }
-            return;
         }
-        self.0.push(dir);
+        sizes
     }
 
-    fn join(&self, dir: String) -> Path {
-        let mut p = self.clone();
-        p.push(dir);
-        p
+    /// The size of the smallest directory that would leave enough free space
+    /// on `disk` if it were deleted.
+    fn smallest_to_free(&self, disk: Disk) -> Option<u64> {
+        let sizes = self.sizes();
+        let free = disk.total.checked_sub(sizes[Self::ROOT])?;
+        let need = disk.required.saturating_sub(free);
+        self.dirs()
+            .map(|id| sizes[id])
+            .filter(|size| *size >= need)
+            .min()
     }
 
-    fn path(&self) -> String {
-        "/".to_owned() + &self.0.join("/")
+    /// Every directory's total size and path, deepest first, like `du`.
+    fn du(&self) -> String {
+        let sizes = self.sizes();
+        let mut out = String::new();
+        self.du_from(Self::ROOT, &sizes, &mut out);
+        out
+    }
+
+    fn du_from(&self, id: NodeId, sizes: &[u64], out: &mut String) {
+        for child in self.children(id) {
+            if matches!(self.nodes[child].kind, Kind::Dir { .. }) {
+                self.du_from(child, sizes, out);
+            }
+        }
+        out.push_str(&format!("{}\t{}\n", sizes[id], self.path(id)));
+    }
+
+    fn fmt_node(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        id: NodeId,
+        depth: usize,
+    ) -> std::fmt::Result {
+        let node = &self.nodes[id];
+        write!(f, "{:indent$}- {} ", "", node.name, indent = depth * 2)?;
+        match node.kind {
+            Kind::Dir { .. } => writeln!(f, "(dir)")?,
+            Kind::File(size) => writeln!(f, "(file, size={})", size)?,
+        }
+        self.children(id)
+            .try_for_each(|child| self.fmt_node(f, child, depth + 1))
+    }
+}
+
+/// Draws the tree the way the puzzle does, with entries sorted by name.
+impl Display for FsTree {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_node(f, Self::ROOT, 0)
     }
 }
 
@@ -112,27 +265,163 @@ impl Path {
 enum Terminal {
     Cd(String),
     Ls,
-    File(File),
+    Entry(Entry),
 }
 
 #[derive(Debug)]
-enum File {
+enum Entry {
     Dir(String),
     File(String, u64),
 }
 
+impl Display for Terminal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Terminal::Cd(dir) => write!(f, "$ cd {}", dir),
+            Terminal::Ls => write!(f, "$ ls"),
+            Terminal::Entry(Entry::Dir(name)) => write!(f, "dir {}", name),
+            Terminal::Entry(Entry::File(name, size)) => {
+                write!(f, "{} {}", size, name)
+            }
+        }
+    }
+}
+
 impl FromStr for Terminal {
-    type Err = ();
+    type Err = anyhow::Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         if let Some(dir) = s.strip_prefix("$ cd ") {
             Ok(Terminal::Cd(dir.to_owned()))
         } else if s == "$ ls" {
             Ok(Terminal::Ls)
+        } else if s.starts_with('$') {
+            bail!("unknown command {:?}", s)
         } else if let Some(dir) = s.strip_prefix("dir ") {
-            Ok(Terminal::File(File::Dir(dir.to_owned())))
+            Ok(Terminal::Entry(Entry::Dir(dir.to_owned())))
         } else {
-            let (size, file) = input::split2(s, " ");
-            Ok(Terminal::File(File::File(file, size)))
+            let (size, file) = s
+                .split_once(' ')
+                .ok_or_else(|| anyhow!("{:?} isn't a file listing", s))?;
+            let size = size
+                .parse()
+                .with_context(|| format!("{:?} isn't a file listing", s))?;
+            Ok(Terminal::Entry(Entry::File(file.to_owned(), size)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "$ cd /
+$ ls
+dir a
+14848514 b.txt
+8504156 c.dat
+dir d
+$ cd a
+$ ls
+dir e
+29116 f
+2557 g
+62596 h.lst
+$ cd e
+$ ls
+584 i
+$ cd ..
+$ cd ..
+$ cd d
+$ ls
+4060174 j
+8033020 d.log
+5626152 d.ext
+7214296 k";
+
+    fn example() -> FsTree {
+        FsTree::replay(&input::parse_str_lines(EXAMPLE)).unwrap()
+    }
+
+    #[test]
+    fn test_sizes() {
+        let fs = example();
+        let sizes = fs.sizes();
+        let dirs: Vec<_> =
+            fs.dirs().map(|id| (fs.path(id), sizes[id])).collect();
+        assert_eq!(
+            dirs,
+            vec![
+                ("/".to_owned(), 48381165),
+                ("/a".to_owned(), 94853),
+                ("/d".to_owned(), 24933642),
+                ("/a/e".to_owned(), 584),
+            ]
+        );
+        // The same sizes, summed the slow way.
+        fn size(fs: &FsTree, id: NodeId) -> u64 {
+            match fs.nodes[id].kind {
+                Kind::File(size) => size,
+                Kind::Dir { .. } => fs.children(id).map(|c| size(fs, c)).sum(),
+            }
         }
+        assert!(fs.dirs().all(|id| size(&fs, id) == sizes[id]));
+        assert_eq!(fs.smallest_to_free(DISK), Some(24933642));
+        let tiny = Disk {
+            total: 40000000,
+            required: 0,
+        };
+        assert_eq!(fs.smallest_to_free(tiny), None);
+    }
+
+    #[test]
+    fn test_render() {
+        let fs = example();
+        assert_eq!(
+            fs.to_string(),
+            "- / (dir)
+  - a (dir)
+    - e (dir)
+      - i (file, size=584)
+    - f (file, size=29116)
+    - g (file, size=2557)
+    - h.lst (file, size=62596)
+  - b.txt (file, size=14848514)
+  - c.dat (file, size=8504156)
+  - d (dir)
+    - d.ext (file, size=5626152)
+    - d.log (file, size=8033020)
+    - j (file, size=4060174)
+    - k (file, size=7214296)
+"
+        );
+        assert_eq!(
+            fs.du(),
+            "584\t/a/e\n94853\t/a\n24933642\t/d\n48381165\t/\n"
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let replay = |s: &str| {
+            let lines: Vec<Terminal> = input::parse_str_lines(s);
+            FsTree::replay(&lines).map_err(|e| format!("{:#}", e))
+        };
+        assert_eq!(
+            replay("$ cd /\n$ cd x").unwrap_err(),
+            "line 2: $ cd x: x hasn't been listed"
+        );
+        assert_eq!(
+            replay("$ ls\ndir x\n$ cd x\n$ cd ..\n$ ls").unwrap_err(),
+            "line 5: $ ls: / was already listed"
+        );
+        assert!(replay("$ ls\n1 f\n$ cd f").is_err());
+        assert!(replay("$ cd ..").is_err());
+        assert_eq!(
+            replay("$ cd /\n$ ls\n1 f\ndir a\n2 f").unwrap_err(),
+            "line 5: 2 f: f is already in /"
+        );
+        assert!(replay("$ cd /\ndir x").is_err());
+        assert!("$ rm -rf /".parse::<Terminal>().is_err());
+        assert!("big file".parse::<Terminal>().is_err());
     }
 }
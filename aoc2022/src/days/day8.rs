@@ -1,16 +1,14 @@
-use util::pos::{DigitGrid, Pos, PosMap, PosSet};
+use util::pos::{DigitGrid, Pos, PosMap};
 
 const INPUT: &str = "input/day8.txt";
 
 pub fn solution() {
     let trees: DigitGrid<u8> = input::parse(INPUT);
     let forest = Forest::from(trees);
+    let survey = forest.survey();
 
-    let visible = forest.visible();
-    println!("Part1: {}", visible.len());
-
-    let part2 = forest.max_scenic_score();
-    println!("Part2: {}", part2);
+    println!("Part1: {}", survey.visible_count());
+    println!("Part2: {}", survey.max_scenic_score());
 }
 
 struct Forest {
@@ -18,84 +16,66 @@ struct Forest {
     max: Pos,
 }
 
-impl Forest {
-    fn visible(&self) -> PosSet {
-        let mut visible = PosSet::new();
-        for row in 0..=self.max.1 {
-            visible.extend(self.visible_in_row(row));
-        }
-        for col in 0..=self.max.0 {
-            visible.extend(self.visible_in_col(col));
-        }
-        visible
-    }
-
-    /// Returns the union of trees that can be seen from the left
-    /// and trees that can be seen from the right in this row.
-    fn visible_in_row(&self, row: i64) -> PosSet {
-        let p = Pos(0, row);
-        let mut visible = PosSet::new();
-        visible.extend(self.visible_from(p.iter_x(self.max.0)));
-        visible.extend(self.visible_from(p.iter_x(self.max.0).rev()));
-        visible
-    }
+/// What can be seen from every tree, and which trees can be seen from
+/// outside the forest.
+#[derive(Debug)]
+struct Survey {
+    visible: PosMap<bool>,
+    scores: PosMap<u64>,
+}
 
-    /// Returns the union of trees that can be seem from the top
-    /// and trees that can be seen from the bottom in this col.
-    fn visible_in_col(&self, col: i64) -> PosSet {
-        let p = Pos(col, 0);
-        let mut visible = PosSet::new();
-        visible.extend(self.visible_from(p.iter_y(self.max.1)));
-        visible.extend(self.visible_from(p.iter_y(self.max.1).rev()));
-        visible
-    }
-
-    /// Returns the set of trees visible from this single direction.
-    fn visible_from(&self, iter: impl Iterator<Item = Pos>) -> PosSet {
-        let mut visible = PosSet::new();
-        let mut max = -1;
-        for p in iter {
-            let height = self.get_height(p) as i16;
-            if height > max {
-                visible.insert(p);
-                max = height;
-            }
-        }
-        visible
+impl Survey {
+    fn visible_count(&self) -> usize {
+        self.visible.values().filter(|v| **v).count()
     }
 
     fn max_scenic_score(&self) -> u64 {
-        self.trees
-            .keys()
-            .copied()
-            .map(|p| self.scenic_score(p))
-            .max()
-            .unwrap()
+        self.scores.values().copied().max().unwrap_or(0)
     }
+}
 
-    fn scenic_score(&self, from: Pos) -> u64 {
-        let up = Pos(from.0, 0).iter_y(from.1).rev();
-        let down = from.iter_y(self.max.1);
-        let left = Pos(0, from.1).iter_x(from.0).rev();
-        let right = from.iter_x(self.max.0);
-
-        let height = self.get_height(from);
-        self.score_range(height, up)
-            * self.score_range(height, down)
-            * self.score_range(height, left)
-            * self.score_range(height, right)
+impl Forest {
+    /// Sweeps every row and column once in each direction, so the whole
+    /// survey is linear in the number of trees.
+    fn survey(&self) -> Survey {
+        let mut survey = Survey {
+            visible: self.trees.keys().map(|&p| (p, false)).collect(),
+            scores: self.trees.keys().map(|&p| (p, 1)).collect(),
+        };
+        let rows = (0..=self.max.1).map(|y| Pos(0, y).iter_x(self.max.0));
+        let cols = (0..=self.max.0).map(|x| Pos(x, 0).iter_y(self.max.1));
+        for line in rows.chain(cols) {
+            let line: Vec<Pos> = line.collect();
+            self.sweep(line.iter().copied(), &mut survey);
+            self.sweep(line.iter().rev().copied(), &mut survey);
+        }
+        survey
     }
 
-    /// Returns the scenic score of the given range [from, to].
-    fn score_range(&self, height: u8, range: impl Iterator<Item = Pos>) -> u64 {
-        let mut count = 0;
-        for p in range.skip(1) {
-            count += 1;
-            if self.get_height(p) >= height {
-                break;
+    /// Walks along a line looking back towards where it started. The stack
+    /// holds the trees that could still block the view of a later tree. Their
+    /// heights never increase, since a tree only pops shorter ones and an
+    /// equal one still blocks the view past it, so each tree is pushed and
+    /// popped at most once.
+    fn sweep(&self, line: impl Iterator<Item = Pos>, survey: &mut Survey) {
+        let mut stack: Vec<(usize, u8)> = Vec::new();
+        for (i, p) in line.enumerate() {
+            let height = self.get_height(p);
+            while stack.last().is_some_and(|&(_, h)| h < height) {
+                stack.pop();
             }
+            let distance = match stack.last() {
+                Some(&(j, _)) => i - j,
+                None => {
+                    // Nothing this tall or taller before it, so it's visible
+                    // from the edge and can see all the way there.
+                    survey.visible.insert(p, true);
+                    i
+                }
+            };
+            *survey.scores.get_mut(&p).unwrap() *= distance as u64;
+            stack.push((i, height));
         }
-        count
     }
 
     fn get_height(&self, p: Pos) -> u8 {
@@ -112,3 +92,25 @@ impl From<DigitGrid<u8>> for Forest {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "30373\n25512\n65332\n33549\n35390";
+
+    #[test]
+    fn test_survey() {
+        let forest = Forest::from(EXAMPLE.parse::<DigitGrid<u8>>().unwrap());
+        let survey = forest.survey();
+        assert_eq!(survey.visible_count(), 21);
+        assert_eq!(survey.max_scenic_score(), 8);
+        assert_eq!(survey.scores[&Pos(2, 1)], 4);
+        assert_eq!(survey.scores[&Pos(2, 3)], 8);
+        assert!(survey.visible[&Pos(1, 1)]);
+        assert!(!survey.visible[&Pos(3, 1)]);
+        assert!(!survey.visible[&Pos(2, 2)]);
+        // Edge trees see nothing in at least one direction.
+        assert_eq!(survey.scores[&Pos(0, 2)], 0);
+    }
+}
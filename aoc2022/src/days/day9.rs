@@ -1,10 +1,14 @@
-use std::str::FromStr;
-use util::pos::{Pos, PosSet};
+use std::slice;
+
+use util::{
+    dir::Step,
+    pos::{Pos, PosSet},
+};
 
 const INPUT: &str = "input/day9.txt";
 
 pub fn solution() {
-    let instructions: Vec<Instruction> = input::parse_lines(INPUT);
+    let instructions: Vec<Step> = input::parse_lines(INPUT);
 
     let mut part1 = Rope::new(2);
     let mut part2 = Rope::new(10);
@@ -14,39 +18,55 @@ pub fn solution() {
     }
     println!(
         "Part1: {}\nPart2: {}",
-        part1.visited.len(),
-        part2.visited.len()
+        part1.tail_visited().len(),
+        part2.tail_visited().len()
     );
+    if crate::options().iter().any(|o| o == "render") {
+        print!("{}", render(&part2.knots));
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Rope {
     knots: Vec<Pos>,
-    visited: PosSet,
+    /// Every position each knot has been in, indexed like `knots`.
+    visited: Vec<PosSet>,
 }
 
 impl Rope {
     fn new(num_knots: usize) -> Self {
+        assert!(num_knots > 0, "a rope needs at least one knot");
         Self {
             knots: vec![Pos(0, 0); num_knots],
-            visited: PosSet::from([Pos(0, 0)]),
+            visited: vec![PosSet::from([Pos(0, 0)]); num_knots],
         }
     }
 
-    fn mv(&mut self, Instruction(direction, magnitude): &Instruction) {
-        let d = match direction {
-            Direction::Up => Pos(0, 1),
-            Direction::Down => Pos(0, -1),
-            Direction::Left => Pos(-1, 0),
-            Direction::Right => Pos(1, 0),
-        };
-        for _ in 0..*magnitude {
-            self.knots[0] += d;
-            let tail = self.knots.len() - 1;
-            for i in 0..tail {
-                self.follow(i, i + 1);
-            }
-            self.visited.insert(self.knots[tail]);
+    fn tail_visited(&self) -> &PosSet {
+        self.visited.last().unwrap()
+    }
+
+    fn mv(&mut self, step: &Step) {
+        self.frames(slice::from_ref(step)).for_each(drop);
+    }
+
+    /// Moves through `steps` one cell at a time, yielding every knot's
+    /// position after each one.
+    fn frames<'a>(&'a mut self, steps: &'a [Step]) -> Frames<'a> {
+        Frames {
+            rope: self,
+            steps: steps.iter(),
+            current: None,
+        }
+    }
+
+    fn step(&mut self, d: Pos) {
+        self.knots[0] += d;
+        for i in 1..self.knots.len() {
+            self.follow(i - 1, i);
+        }
+        for (visited, knot) in self.visited.iter_mut().zip(&self.knots) {
+            visited.insert(*knot);
         }
     }
 
@@ -71,32 +91,134 @@ impl Rope {
     }
 }
 
-enum Direction {
-    Up,
-    Down,
-    Left,
-    Right,
+struct Frames<'a> {
+    rope: &'a mut Rope,
+    steps: slice::Iter<'a, Step>,
+    /// The direction of the step in progress and the steps it has left.
+    current: Option<(Pos, i64)>,
+}
+
+impl Iterator for Frames<'_> {
+    type Item = Vec<Pos>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match &mut self.current {
+                Some((d, n)) if *n > 0 => {
+                    *n -= 1;
+                    let d = *d;
+                    self.rope.step(d);
+                    return Some(self.rope.knots.clone());
+                }
+                _ => {
+                    let step = self.steps.next()?;
+                    self.current = Some((step.delta(), step.n));
+                }
+            }
+        }
+    }
 }
 
-struct Instruction(Direction, usize);
-
-impl FromStr for Direction {
-    type Err = ();
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(match s.chars().next().unwrap() {
-            'U' => Direction::Up,
-            'D' => Direction::Down,
-            'L' => Direction::Left,
-            'R' => Direction::Right,
-            c => panic!("Unknown direction {}", c),
-        })
+/// Draws a frame the way the puzzle does: `H` for the head, then each knot
+/// by its index (or `T` for a two-knot rope's tail), `s` for the start and
+/// `.` elsewhere. Earlier knots hide later ones, and y increases upwards.
+fn render(frame: &[Pos]) -> String {
+    let label = |i: usize| match i {
+        0 => 'H',
+        1 if frame.len() == 2 => 'T',
+        1..=9 => char::from_digit(i as u32, 10).unwrap(),
+        _ => '#',
+    };
+    let all = || frame.iter().chain([&Pos(0, 0)]);
+    let (min_x, max_x) = (all().map(|p| p.0).min(), all().map(|p| p.0).max());
+    let (min_y, max_y) = (all().map(|p| p.1).min(), all().map(|p| p.1).max());
+    let (min_x, max_x) = (min_x.unwrap(), max_x.unwrap());
+    let (min_y, max_y) = (min_y.unwrap(), max_y.unwrap());
+
+    let mut out = String::new();
+    for y in (min_y..=max_y).rev() {
+        for x in min_x..=max_x {
+            let p = Pos(x, y);
+            out.push(match frame.iter().position(|&k| k == p) {
+                Some(i) => label(i),
+                None if p == Pos(0, 0) => 's',
+                None => '.',
+            });
+        }
+        out.push('\n');
     }
+    out
 }
 
-impl FromStr for Instruction {
-    type Err = ();
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (direction, magnitude) = input::split2(s, " ");
-        Ok(Self(direction, magnitude))
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use util::{dir::Dir, rng::Lcg};
+
+    const EXAMPLE: &str = "R 4\nU 4\nL 3\nD 1\nR 4\nD 1\nL 5\nR 2";
+    const LARGER: &str = "R 5\nU 8\nL 8\nD 3\nR 17\nD 10\nL 25\nU 20";
+
+    #[test]
+    fn test_visited() {
+        let commands: Vec<Step> = input::parse_str_lines(EXAMPLE);
+        let mut rope = Rope::new(2);
+        commands.iter().for_each(|c| rope.mv(c));
+        assert_eq!(rope.tail_visited().len(), 13);
+        assert_eq!(rope.visited.len(), 2);
+
+        let commands: Vec<Step> = input::parse_str_lines(LARGER);
+        let mut rope = Rope::new(10);
+        commands.iter().for_each(|c| rope.mv(c));
+        assert_eq!(rope.tail_visited().len(), 36);
+    }
+
+    #[test]
+    fn test_frames() {
+        let commands: Vec<Step> = input::parse_str_lines(EXAMPLE);
+        let mut rope = Rope::new(2);
+        let frames: Vec<_> = rope.frames(&commands).collect();
+        assert_eq!(frames.len(), 24);
+        assert_eq!(frames[3], vec![Pos(4, 0), Pos(3, 0)]);
+        assert_eq!(render(&frames[3]), "s..TH\n");
+        assert_eq!(render(&frames[7]), "....H\n....T\n.....\n.....\ns....\n");
+        assert_eq!(frames.last(), Some(&rope.knots));
+    }
+
+    #[test]
+    fn test_render() {
+        let commands: Vec<Step> = input::parse_str_lines("R 5\nU 8");
+        let mut rope = Rope::new(10);
+        let last = rope.frames(&commands).last().unwrap();
+        // The start is hidden under the last knot.
+        let expected = [
+            ".....H", ".....1", ".....2", ".....3", "....54", "...6..",
+            "..7...", ".8....", "9.....",
+        ];
+        assert_eq!(render(&last), expected.join("\n") + "\n");
+    }
+
+    #[test]
+    fn test_knots_stay_adjacent() {
+        // Random walks with random rope lengths should never stretch a rope.
+        let mut rng = Lcg::new(0x2022);
+        let dirs = [Dir::Up, Dir::Down, Dir::Left, Dir::Right];
+        for _ in 0..200 {
+            let commands: Vec<_> = (0..50)
+                .map(|_| {
+                    Step::new(
+                        dirs[rng.below(4) as usize],
+                        rng.below(10) as i64 + 1,
+                    )
+                    .unwrap()
+                })
+                .collect();
+            let mut rope = Rope::new(rng.below(15) as usize + 1);
+            for frame in rope.frames(&commands) {
+                for pair in frame.windows(2) {
+                    let Pos(dx, dy) = pair[0].abs_diff(pair[1]);
+                    assert!(dx <= 1 && dy <= 1, "{:?}", frame);
+                }
+            }
+        }
     }
 }
@@ -28,3 +28,8 @@ fn main() {
 
     days[day]();
 }
+
+/// Any arguments after the day, for solutions that take settings.
+fn options() -> Vec<String> {
+    std::env::args().skip(2).collect()
+}
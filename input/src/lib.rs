@@ -1,3 +1,4 @@
+use anyhow::anyhow;
 use std::{
     fmt::Debug,
     fs::{self, File},
@@ -6,6 +7,8 @@ use std::{
     str::FromStr,
 };
 
+pub mod scan;
+
 pub fn input_path<P: AsRef<Path>>(relative: P) -> PathBuf {
     Path::new(&std::env::var("CARGO_MANIFEST_DIR").unwrap()).join(relative)
 }
@@ -50,6 +53,54 @@ where
         .collect()
 }
 
+/// Splits `s` into records separated by one or more blank lines. Lines in a
+/// record are joined with `\n`, whatever line endings the input used.
+pub fn paragraphs(s: &str) -> Vec<String> {
+    let mut records = Vec::new();
+    let mut record: Vec<&str> = Vec::new();
+    for line in s.lines() {
+        if line.trim().is_empty() {
+            if !record.is_empty() {
+                records.push(record.join("\n"));
+                record.clear();
+            }
+        } else {
+            record.push(line);
+        }
+    }
+    if !record.is_empty() {
+        records.push(record.join("\n"));
+    }
+    records
+}
+
+/// Parses each blank-line-separated record of `s` as an `I`. Errors say which
+/// record failed, counting from 1.
+pub fn parse_str_paragraphs<I>(s: &str) -> anyhow::Result<Vec<I>>
+where
+    I: FromStr,
+    I::Err: Debug,
+{
+    paragraphs(s)
+        .iter()
+        .enumerate()
+        .map(|(i, r)| {
+            r.parse().map_err(|e| anyhow!("record {}: {:?}", i + 1, e))
+        })
+        .collect()
+}
+
+/// Parses each blank-line-separated record of the input in path `p` as an
+/// `I`.
+pub fn parse_paragraphs<I, P>(p: P) -> anyhow::Result<Vec<I>>
+where
+    I: FromStr,
+    I::Err: Debug,
+    P: AsRef<Path>,
+{
+    parse_str_paragraphs(&fs::read_to_string(p)?)
+}
+
 pub struct SpaceVec<T>(pub Vec<T>);
 impl<T: FromStr> FromStr for SpaceVec<T> {
     type Err = T::Err;
@@ -161,6 +212,21 @@ mod tests {
         assert!(v.is_empty());
     }
 
+    #[test]
+    fn test_paragraphs() {
+        let s = "\n\na\r\nb\r\n\r\n  \n\nc\n";
+        assert_eq!(paragraphs(s), vec!["a\nb", "c"]);
+        assert!(paragraphs("\n\n").is_empty());
+
+        let sums: Vec<SpaceVec<u8>> =
+            parse_str_paragraphs("1\n2\n\n3").unwrap();
+        assert_eq!(sums.len(), 2);
+        let err = parse_str_paragraphs::<SpaceVec<u8>>("1\n\n2\nx")
+            .err()
+            .unwrap();
+        assert!(err.to_string().starts_with("record 2: "), "{}", err);
+    }
+
     #[test]
     fn test_split2() {
         assert_eq!((1, -2), split2::<u8, i8>("1,-2", ","));
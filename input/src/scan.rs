@@ -0,0 +1,434 @@
+//! Line formats for the [`scan!`](crate::scan!) macro.
+//!
+//! A format is literal text with `{name}` placeholders, e.g.
+//! `"x={x}, y={y}"`. Each placeholder captures everything up to the next bit
+//! of literal text, or the rest of the line if it comes last, so two
+//! placeholders always need some text between them. `{name:sep}` captures a
+//! list, split on `sep`. `{_}` matches some text and throws it away, and
+//! `{{` and `}}` stand for literal braces.
+
+use std::{fmt::Debug, marker::PhantomData, str::FromStr};
+
+use anyhow::{anyhow, bail};
+
+/// The error type of every `FromStr` impl that `scan!` generates.
+pub type Error = anyhow::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Piece {
+    Literal(String),
+    Field { name: String, sep: Option<String> },
+}
+
+/// A parsed format string.
+#[derive(Debug)]
+pub struct Format {
+    pieces: Vec<Piece>,
+}
+
+impl Format {
+    /// Parses `format`, checking that its placeholders are exactly `fields`.
+    pub fn new(format: &str, fields: &[&str]) -> anyhow::Result<Format> {
+        let mut pieces = Vec::new();
+        let mut literal = String::new();
+        let mut chars = format.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let rest = chars.as_str();
+                    let end = rest.find('}').ok_or_else(|| {
+                        anyhow!("unclosed {{ in {:?}", format)
+                    })?;
+                    let (name, sep) = match rest[..end].split_once(':') {
+                        Some((name, sep)) => (name, Some(sep.to_string())),
+                        None => (&rest[..end], None),
+                    };
+                    if !is_ident(name) {
+                        bail!("bad placeholder {{{}}}", &rest[..end]);
+                    }
+                    if sep.as_deref() == Some("") {
+                        bail!("empty separator for `{}`", name);
+                    }
+                    if !literal.is_empty() {
+                        pieces.push(Piece::Literal(literal.split_off(0)));
+                    } else if let Some(Piece::Field { name: prev, .. }) =
+                        pieces.last()
+                    {
+                        bail!("nothing separates `{}` from `{}`", prev, name);
+                    }
+                    pieces.push(Piece::Field {
+                        name: name.to_string(),
+                        sep,
+                    });
+                    chars = rest[end + 1..].chars();
+                }
+                '}' => bail!("unmatched }} in {:?}", format),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            pieces.push(Piece::Literal(literal));
+        }
+
+        let names: Vec<&str> = pieces
+            .iter()
+            .filter_map(|p| match p {
+                Piece::Field { name, .. } if name != "_" => Some(name.as_str()),
+                _ => None,
+            })
+            .collect();
+        for field in fields {
+            match names.iter().filter(|&n| n == field).count() {
+                1 => {}
+                0 => bail!("`{}` is missing from {:?}", field, format),
+                _ => {
+                    bail!("`{}` appears more than once in {:?}", field, format)
+                }
+            }
+        }
+        if let Some(extra) = names.iter().find(|n| !fields.contains(n)) {
+            bail!("`{}` isn't a field", extra);
+        }
+        Ok(Format { pieces })
+    }
+
+    /// Matches `s` against the format. A single trailing line ending is
+    /// ignored.
+    pub fn captures<'a>(&'a self, s: &'a str) -> anyhow::Result<Captures<'a>> {
+        let s = s.strip_suffix('\n').unwrap_or(s);
+        let s = s.strip_suffix('\r').unwrap_or(s);
+        let column = |rest: &str| s.len() - rest.len() + 1;
+        let mut captures = Vec::new();
+        let mut rest = s;
+        for (i, piece) in self.pieces.iter().enumerate() {
+            match piece {
+                Piece::Literal(literal) => {
+                    let Some(tail) = rest.strip_prefix(literal.as_str()) else {
+                        bail!(
+                            "expected {:?} at column {} of {:?}, found {:?}",
+                            literal,
+                            column(rest),
+                            s,
+                            rest
+                        );
+                    };
+                    rest = tail;
+                }
+                Piece::Field { name, sep } => {
+                    let end = match self.pieces.get(i + 1) {
+                        Some(Piece::Literal(next)) => {
+                            rest.find(next.as_str()).ok_or_else(|| {
+                                anyhow!(
+                                    "expected {:?} after `{}` in {:?}",
+                                    next,
+                                    name,
+                                    s
+                                )
+                            })?
+                        }
+                        _ => rest.len(),
+                    };
+                    if end == 0 {
+                        bail!(
+                            "missing `{}` at column {} of {:?}",
+                            name,
+                            column(rest),
+                            s
+                        );
+                    }
+                    if name != "_" {
+                        captures.push(Capture {
+                            name,
+                            text: &rest[..end],
+                            sep: sep.as_deref(),
+                        });
+                    }
+                    rest = &rest[end..];
+                }
+            }
+        }
+        if !rest.is_empty() {
+            bail!(
+                "unexpected {:?} at column {} of {:?}",
+                rest,
+                column(rest),
+                s
+            );
+        }
+        Ok(Captures(captures))
+    }
+}
+
+fn is_ident(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|c| c == '_' || c.is_ascii_alphabetic())
+        && chars.all(|c| c == '_' || c.is_ascii_alphanumeric())
+}
+
+/// The text each named placeholder matched.
+#[derive(Debug)]
+pub struct Captures<'a>(Vec<Capture<'a>>);
+
+impl<'a> Captures<'a> {
+    /// Panics if the format has no such field; [`Format::new`] rules that
+    /// out for the fields it was given.
+    pub fn get(&self, name: &str) -> &Capture<'a> {
+        self.0
+            .iter()
+            .find(|c| c.name == name)
+            .unwrap_or_else(|| panic!("no field `{}`", name))
+    }
+}
+
+#[derive(Debug)]
+pub struct Capture<'a> {
+    pub name: &'a str,
+    pub text: &'a str,
+    pub sep: Option<&'a str>,
+}
+
+impl Capture<'_> {
+    pub fn parse<T>(&self) -> anyhow::Result<T>
+    where
+        T: FromStr,
+        T::Err: Debug,
+    {
+        self.text.parse().map_err(|e| {
+            anyhow!("`{}` from {:?}: {:?}", self.name, self.text, e)
+        })
+    }
+
+    pub fn parse_list<T>(&self) -> anyhow::Result<Vec<T>>
+    where
+        T: FromStr,
+        T::Err: Debug,
+    {
+        let sep = self
+            .sep
+            .ok_or_else(|| anyhow!("`{}` needs a separator", self.name))?;
+        self.text
+            .split(sep)
+            .enumerate()
+            .map(|(i, item)| {
+                item.parse().map_err(|e| {
+                    anyhow!(
+                        "`{}` item {} from {:?}: {:?}",
+                        self.name,
+                        i + 1,
+                        item,
+                        e
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+/// Lets `scan!` pick [`Capture::parse_list`] for `Vec` fields and
+/// [`Capture::parse`] for everything else: method lookup tries `ScanList`'s
+/// receiver, `&Slot<Vec<T>>`, before `ScanOne`'s `&&Slot<T>`.
+#[doc(hidden)]
+pub struct Slot<T>(PhantomData<T>);
+
+impl<T> Slot<T> {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Slot(PhantomData)
+    }
+}
+
+#[doc(hidden)]
+pub trait ScanList<T> {
+    fn scan(&self, capture: &Capture) -> anyhow::Result<T>;
+}
+
+impl<T> ScanList<Vec<T>> for Slot<Vec<T>>
+where
+    T: FromStr,
+    T::Err: Debug,
+{
+    fn scan(&self, capture: &Capture) -> anyhow::Result<Vec<T>> {
+        capture.parse_list()
+    }
+}
+
+#[doc(hidden)]
+pub trait ScanOne<T> {
+    fn scan(&self, capture: &Capture) -> anyhow::Result<T>;
+}
+
+impl<T> ScanOne<T> for &Slot<T>
+where
+    T: FromStr,
+    T::Err: Debug,
+{
+    fn scan(&self, capture: &Capture) -> anyhow::Result<T> {
+        capture.parse()
+    }
+}
+
+/// Defines a struct along with a `FromStr` impl that fills its fields from
+/// a line matching a [format](self). `Vec` fields must use a list
+/// placeholder; every other field is parsed with its own `FromStr`.
+///
+/// ```
+/// input::scan! {
+///     #[format("move {n} from {from} to {to}")]
+///     #[derive(Debug)]
+///     struct Move {
+///         n: usize,
+///         from: usize,
+///         to: usize,
+///     }
+/// }
+///
+/// let m: Move = "move 3 from 1 to 2".parse().unwrap();
+/// assert_eq!((m.n, m.from, m.to), (3, 1, 2));
+/// ```
+#[macro_export]
+macro_rules! scan {
+    (
+        #[format($format:literal)]
+        $(#[$attr:meta])*
+        $vis:vis struct $name:ident {
+            $($field_vis:vis $field:ident : $ty:ty),* $(,)?
+        }
+    ) => {
+        $(#[$attr])*
+        $vis struct $name {
+            $($field_vis $field: $ty),*
+        }
+
+        impl ::std::str::FromStr for $name {
+            type Err = $crate::scan::Error;
+            fn from_str(s: &str) -> ::std::result::Result<Self, Self::Err> {
+                #[allow(unused_imports)]
+                use $crate::scan::{ScanList as _, ScanOne as _};
+                static FORMAT: ::std::sync::OnceLock<$crate::scan::Format> =
+                    ::std::sync::OnceLock::new();
+                let format = FORMAT.get_or_init(|| {
+                    $crate::scan::Format::new(
+                        $format,
+                        &[$(stringify!($field)),*],
+                    )
+                    .unwrap_or_else(|e| panic!("{}: {}", stringify!($name), e))
+                });
+                let captures = format.captures(s)?;
+                Ok($name {
+                    $($field: (&$crate::scan::Slot::<$ty>::new())
+                        .scan(captures.get(stringify!($field)))?,)*
+                })
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    crate::scan! {
+        #[format("Sensor at x={x}, y={y}: closest beacon is at x={bx}, y={by}")]
+        #[derive(Debug, PartialEq)]
+        struct Reading {
+            x: i64,
+            y: i64,
+            bx: i64,
+            by: i64,
+        }
+    }
+
+    crate::scan! {
+        #[format("Valve {name} has flow rate={rate}; {_} {_} to {_} {to:, }")]
+        #[derive(Debug)]
+        struct Valve {
+            name: String,
+            rate: u32,
+            to: Vec<String>,
+        }
+    }
+
+    crate::scan! {
+        #[format("{points: -> }")]
+        struct Path {
+            points: Vec<u8>,
+        }
+    }
+
+    #[test]
+    fn test_scan() {
+        let r: Reading =
+            "Sensor at x=2, y=-18: closest beacon is at x=-2, y=15\n"
+                .parse()
+                .unwrap();
+        assert_eq!(
+            r,
+            Reading {
+                x: 2,
+                y: -18,
+                bx: -2,
+                by: 15
+            }
+        );
+
+        let v: Valve =
+            "Valve AA has flow rate=0; tunnels lead to valves DD, II"
+                .parse()
+                .unwrap();
+        assert_eq!((v.name.as_str(), v.rate), ("AA", 0));
+        assert_eq!(v.to, vec!["DD", "II"]);
+        let v: Valve = "Valve HH has flow rate=22; tunnel leads to valve GG"
+            .parse()
+            .unwrap();
+        assert_eq!(v.to, vec!["GG"]);
+
+        let Path { points } = "1 -> 2 -> 3".parse().unwrap();
+        assert_eq!(points, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_scan_errors() {
+        let err = |s: &str| s.parse::<Reading>().unwrap_err().to_string();
+        assert!(err("Sensor at x=2, y=-18: beacon at x=-2, y=15")
+            .starts_with("expected \": closest beacon is at x=\" after `y`"),);
+        assert!(err("Sensor at x=2,y=-18")
+            .starts_with("expected \", y=\" after `x`"));
+        assert!(err("Sensor at x=, y=1: closest beacon is at x=2, y=3")
+            .starts_with("missing `x` at column 13"));
+        assert!(err("Sensor at x=a, y=1: closest beacon is at x=2, y=3")
+            .starts_with("`x` from \"a\": "));
+        assert!(err("Beacon at x=1, y=1: closest beacon is at x=2, y=3")
+            .starts_with("expected \"Sensor at x=\" at column 1"));
+        let err = "1 -> 2 -> x".parse::<Path>().err().unwrap().to_string();
+        assert!(err.starts_with("`points` item 3 from \"x\": "), "{}", err);
+    }
+
+    #[test]
+    fn test_format() {
+        let format = Format::new("{{{a}}}: {b}", &["a", "b"]).unwrap();
+        let captures = format.captures("{1}: 2").unwrap();
+        assert_eq!(captures.get("a").text, "1");
+        assert_eq!(captures.get("b").text, "2");
+        assert!(format.captures("{1}: 2 ").is_ok());
+        assert!(format.captures("{1} : 2").is_err());
+
+        assert!(Format::new("{a}{b}", &["a", "b"]).is_err());
+        assert!(Format::new("{a} {a}", &["a"]).is_err());
+        assert!(Format::new("{a}", &["a", "b"]).is_err());
+        assert!(Format::new("{a} {b}", &["a"]).is_err());
+        assert!(Format::new("{a:}", &["a"]).is_err());
+        assert!(Format::new("{a", &["a"]).is_err());
+        assert!(Format::new("a}", &[]).is_err());
+        assert!(Format::new("{1a}", &[]).is_err());
+    }
+}
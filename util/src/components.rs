@@ -0,0 +1,208 @@
+//! Region finding on grids: flood fill from a single cell, and labelling of
+//! every connected component at once.
+
+use crate::pos::{Pos, PosMap, PosSet};
+
+/// Which cells count as adjacent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Connectivity {
+    /// Up, down, left and right.
+    Four,
+    /// Diagonals too.
+    Eight,
+}
+
+impl Connectivity {
+    pub fn neighbors(&self, p: Pos) -> Vec<Pos> {
+        match self {
+            Connectivity::Four => p.compass_neighbors().to_vec(),
+            Connectivity::Eight => p.neighbors().to_vec(),
+        }
+    }
+}
+
+/// The cells reachable from `start` through passable cells, including
+/// `start` itself if it's passable.
+pub fn flood_fill<T>(
+    grid: &PosMap<T>,
+    start: Pos,
+    connectivity: Connectivity,
+    passable: impl Fn(Pos, &T) -> bool,
+) -> PosSet {
+    let is_passable = |p: &Pos| grid.get(p).is_some_and(|t| passable(*p, t));
+    let mut visited = PosSet::new();
+    if !is_passable(&start) {
+        return visited;
+    }
+    visited.insert(start);
+    let mut stack = vec![start];
+    while let Some(cur) = stack.pop() {
+        for n in connectivity.neighbors(cur) {
+            // Mark cells as they're pushed so each is only pushed once.
+            if is_passable(&n) && visited.insert(n) {
+                stack.push(n);
+            }
+        }
+    }
+    visited
+}
+
+/// The connected components of the passable cells in a grid. Components are
+/// numbered in order of their smallest cell.
+#[derive(Clone, Debug, Default)]
+pub struct Components {
+    labels: PosMap<usize>,
+    sizes: Vec<usize>,
+    representatives: Vec<Pos>,
+}
+
+impl Components {
+    pub fn label<T>(
+        grid: &PosMap<T>,
+        connectivity: Connectivity,
+        passable: impl Fn(Pos, &T) -> bool,
+    ) -> Components {
+        let mut cells: Vec<Pos> = grid
+            .iter()
+            .filter(|(p, t)| passable(**p, t))
+            .map(|(p, _)| *p)
+            .collect();
+        cells.sort();
+        let index: PosMap<usize> =
+            cells.iter().enumerate().map(|(i, p)| (*p, i)).collect();
+
+        let mut sets = UnionFind::new(cells.len());
+        for (i, p) in cells.iter().enumerate() {
+            for n in connectivity.neighbors(*p) {
+                if let Some(&j) = index.get(&n) {
+                    sets.union(i, j);
+                }
+            }
+        }
+
+        let mut components = Components::default();
+        let mut ids = PosMap::new();
+        for (i, p) in cells.iter().enumerate() {
+            let root = cells[sets.find(i)];
+            let id = *ids.entry(root).or_insert_with(|| {
+                components.sizes.push(0);
+                components.representatives.push(*p);
+                components.sizes.len() - 1
+            });
+            components.sizes[id] += 1;
+            components.labels.insert(*p, id);
+        }
+        components
+    }
+
+    /// The number of components.
+    pub fn len(&self) -> usize {
+        self.sizes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sizes.is_empty()
+    }
+
+    /// The component containing `p`, or `None` if `p` isn't passable.
+    pub fn id(&self, p: Pos) -> Option<usize> {
+        self.labels.get(&p).copied()
+    }
+
+    /// Every passable cell, mapped to its component.
+    pub fn labels(&self) -> &PosMap<usize> {
+        &self.labels
+    }
+
+    /// The number of cells in each component, indexed by id.
+    pub fn sizes(&self) -> &[usize] {
+        &self.sizes
+    }
+
+    /// The smallest cell in each component, indexed by id.
+    pub fn representatives(&self) -> &[Pos] {
+        &self.representatives
+    }
+
+    /// The ids of the `n` largest components, biggest first.
+    pub fn largest(&self, n: usize) -> Vec<usize> {
+        let mut ids: Vec<usize> = (0..self.len()).collect();
+        ids.sort_by_key(|&id| std::cmp::Reverse(self.sizes[id]));
+        ids.truncate(n);
+        ids
+    }
+}
+
+/// Disjoint sets with path halving and union by size.
+struct UnionFind {
+    parent: Vec<usize>,
+    size: Vec<usize>,
+}
+
+impl UnionFind {
+    fn new(n: usize) -> Self {
+        UnionFind {
+            parent: (0..n).collect(),
+            size: vec![1; n],
+        }
+    }
+
+    fn find(&mut self, mut i: usize) -> usize {
+        while self.parent[i] != i {
+            self.parent[i] = self.parent[self.parent[i]];
+            i = self.parent[i];
+        }
+        i
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return;
+        }
+        if self.size[a] < self.size[b] {
+            std::mem::swap(&mut a, &mut b);
+        }
+        self.parent[b] = a;
+        self.size[a] += self.size[b];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pos::DigitGrid;
+
+    fn grid(s: &str) -> PosMap<u8> {
+        s.parse::<DigitGrid<u8>>().unwrap().0
+    }
+
+    #[test]
+    fn test_flood_fill() {
+        let g = grid("101\n111\n000");
+        let open = |_, d: &u8| *d == 1;
+        assert_eq!(
+            flood_fill(&g, Pos(0, 0), Connectivity::Four, open).len(),
+            5
+        );
+        assert!(flood_fill(&g, Pos(1, 0), Connectivity::Four, open).is_empty());
+    }
+
+    #[test]
+    fn test_label() {
+        let g = grid("1001\n0110\n0000\n1011");
+        let open = |_, d: &u8| *d == 1;
+
+        let four = Components::label(&g, Connectivity::Four, open);
+        assert_eq!(four.len(), 5);
+        assert_eq!(four.sizes(), &[1, 1, 2, 2, 1]);
+        assert_eq!(four.id(Pos(1, 1)), four.id(Pos(2, 1)));
+        assert_eq!(four.id(Pos(1, 0)), None);
+        assert_eq!(four.representatives()[four.largest(1)[0]], Pos(1, 1));
+
+        let eight = Components::label(&g, Connectivity::Eight, open);
+        assert_eq!(eight.len(), 3);
+        assert_eq!(eight.sizes(), &[4, 1, 2]);
+        assert_eq!(eight.representatives(), &[Pos(0, 0), Pos(0, 3), Pos(2, 3)]);
+    }
+}
@@ -0,0 +1,138 @@
+//! Checking that nested delimiters are balanced, for any set of delimiter
+//! pairs. Characters that aren't delimiters are skipped.
+
+/// The result of checking a line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Check {
+    /// Every delimiter was closed.
+    Balanced,
+    /// A closing delimiter didn't match the innermost open one. `expected`
+    /// is `None` if nothing was open.
+    Corrupt {
+        offset: usize,
+        found: char,
+        expected: Option<char>,
+        score: u64,
+    },
+    /// The line ended with delimiters still open. `completion` closes them.
+    Incomplete { completion: String, score: u64 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Delimiter {
+    open: char,
+    close: char,
+    syntax_score: u64,
+    completion_score: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct DelimiterChecker {
+    delimiters: Vec<Delimiter>,
+}
+
+impl DelimiterChecker {
+    /// Builds a checker from a table of `(open, close, syntax score,
+    /// completion score)`.
+    pub fn new(table: &[(char, char, u64, u64)]) -> Self {
+        let delimiters = table
+            .iter()
+            .map(|&(open, close, syntax_score, completion_score)| Delimiter {
+                open,
+                close,
+                syntax_score,
+                completion_score,
+            })
+            .collect();
+        DelimiterChecker { delimiters }
+    }
+
+    /// Checks `s`, stopping at the first corrupt character. Corrupt lines
+    /// score the syntax score of the offending closer. Incomplete lines
+    /// score each closer in the completion in turn, multiplying the running
+    /// total by five before adding the next.
+    pub fn check(&self, s: &str) -> Check {
+        let mut stack: Vec<&Delimiter> = Vec::new();
+        for (offset, c) in s.char_indices() {
+            if let Some(d) = self.delimiters.iter().find(|d| d.open == c) {
+                stack.push(d);
+            } else if let Some(d) =
+                self.delimiters.iter().find(|d| d.close == c)
+            {
+                match stack.pop() {
+                    Some(top) if top.close == c => {}
+                    top => {
+                        return Check::Corrupt {
+                            offset,
+                            found: c,
+                            expected: top.map(|t| t.close),
+                            score: d.syntax_score,
+                        }
+                    }
+                }
+            }
+        }
+        if stack.is_empty() {
+            return Check::Balanced;
+        }
+        let completion = stack.iter().rev().map(|d| d.close).collect();
+        let score = stack
+            .iter()
+            .rev()
+            .fold(0, |score, d| score * 5 + d.completion_score);
+        Check::Incomplete { completion, score }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        let checker = DelimiterChecker::new(&[
+            ('(', ')', 3, 1),
+            ('[', ']', 57, 2),
+            ('{', '}', 1197, 3),
+            ('<', '>', 25137, 4),
+        ]);
+        assert_eq!(checker.check("([]{<>})"), Check::Balanced);
+        assert_eq!(
+            checker.check("{([(<{}[<>[]}>{[]{[(<()>"),
+            Check::Corrupt {
+                offset: 12,
+                found: '}',
+                expected: Some(']'),
+                score: 1197
+            }
+        );
+        assert_eq!(
+            checker.check("<{([{{}}[<[[[<>{}]]]>[]]"),
+            Check::Incomplete {
+                completion: "])}>".to_string(),
+                score: 294
+            }
+        );
+        assert!(matches!(
+            checker.check("())"),
+            Check::Corrupt {
+                offset: 2,
+                expected: None,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_list_packets() {
+        let checker = DelimiterChecker::new(&[('[', ']', 1, 1)]);
+        assert_eq!(
+            checker.check("[1,[2,[3,[4,[5,6,7]]]],8,9]"),
+            Check::Balanced
+        );
+        assert!(matches!(
+            checker.check("[[1],[2,3,4]"),
+            Check::Incomplete { .. }
+        ));
+    }
+}
@@ -0,0 +1,134 @@
+//! Directions and movement commands shared by the navigation puzzles.
+
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail};
+
+use crate::pos::Pos;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Dir {
+    Up,
+    Down,
+    Left,
+    Right,
+    /// Whichever way we happen to be facing.
+    Forward,
+}
+
+impl Dir {
+    /// A unit step in this direction, with y increasing upwards. `Forward`
+    /// depends on who's moving, so it has none.
+    pub fn delta(&self) -> Option<Pos> {
+        match self {
+            Dir::Up => Some(Pos(0, 1)),
+            Dir::Down => Some(Pos(0, -1)),
+            Dir::Left => Some(Pos(-1, 0)),
+            Dir::Right => Some(Pos(1, 0)),
+            Dir::Forward => None,
+        }
+    }
+}
+
+/// Accepts either the word (`up`) or its initial (`U`).
+impl FromStr for Dir {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "U" | "up" => Dir::Up,
+            "D" | "down" => Dir::Down,
+            "L" | "left" => Dir::Left,
+            "R" | "right" => Dir::Right,
+            "F" | "forward" => Dir::Forward,
+            _ => bail!("unknown direction {:?}", s),
+        })
+    }
+}
+
+/// A direction and a distance, written like `forward 5` or `R 4`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Command {
+    pub dir: Dir,
+    pub n: i64,
+}
+
+impl FromStr for Command {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (dir, n) = s
+            .trim()
+            .split_once(' ')
+            .ok_or_else(|| anyhow!("couldn't split {:?} on ' '", s))?;
+        Ok(Command {
+            dir: dir.parse()?,
+            n: n.parse()?,
+        })
+    }
+}
+
+/// A move on a grid, written like `R 4`. Unlike a `Command` it can't be
+/// `Forward`, so it always has a delta.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Step {
+    dir: Dir,
+    pub n: i64,
+}
+
+impl Step {
+    pub fn new(dir: Dir, n: i64) -> anyhow::Result<Step> {
+        if dir == Dir::Forward {
+            bail!("a step needs an absolute direction, not forward");
+        }
+        Ok(Step { dir, n })
+    }
+
+    pub fn dir(&self) -> Dir {
+        self.dir
+    }
+
+    /// A unit step in this direction, with y increasing upwards.
+    pub fn delta(&self) -> Pos {
+        self.dir.delta().expect("steps are never forward")
+    }
+}
+
+impl TryFrom<Command> for Step {
+    type Error = anyhow::Error;
+    fn try_from(Command { dir, n }: Command) -> Result<Self, Self::Error> {
+        Step::new(dir, n)
+    }
+}
+
+impl FromStr for Step {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<Command>()?.try_into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            "forward 5".parse::<Command>().unwrap(),
+            Command {
+                dir: Dir::Forward,
+                n: 5
+            }
+        );
+        assert_eq!("R 4".parse::<Command>().unwrap().dir, Dir::Right);
+        assert!("sideways 1".parse::<Command>().is_err());
+        assert!("up".parse::<Command>().is_err());
+    }
+
+    #[test]
+    fn test_step() {
+        let step: Step = "U 3".parse().unwrap();
+        assert_eq!((step.dir(), step.n, step.delta()), (Dir::Up, 3, Pos(0, 1)));
+        assert!("F 3".parse::<Step>().is_err());
+        assert!("forward 3".parse::<Step>().is_err());
+    }
+}
@@ -0,0 +1,228 @@
+//! Inclusive integer intervals, and sweep-line queries over lists of them.
+
+use std::{collections::BTreeSet, fmt::Display, str::FromStr};
+
+use anyhow::{anyhow, bail};
+
+/// The integers from `start` to `end`, inclusive. `start` never exceeds
+/// `end`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Interval {
+    start: i64,
+    end: i64,
+}
+
+impl Interval {
+    pub fn new(start: i64, end: i64) -> anyhow::Result<Interval> {
+        if start > end {
+            bail!("interval {}-{} ends before it starts", start, end);
+        }
+        Ok(Interval { start, end })
+    }
+
+    pub fn start(&self) -> i64 {
+        self.start
+    }
+
+    pub fn end(&self) -> i64 {
+        self.end
+    }
+
+    pub fn len(&self) -> u64 {
+        self.start.abs_diff(self.end) + 1
+    }
+
+    /// Intervals always hold at least one integer.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    pub fn contains(&self, other: &Interval) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
+
+    pub fn contains_point(&self, x: i64) -> bool {
+        self.start <= x && x <= self.end
+    }
+
+    pub fn overlaps(&self, other: &Interval) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+
+    pub fn intersection(&self, other: &Interval) -> Option<Interval> {
+        Interval::new(self.start.max(other.start), self.end.min(other.end)).ok()
+    }
+}
+
+impl Display for Interval {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
+
+/// Parses `start-end`. Either end may be negative.
+impl FromStr for Interval {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let split = s
+            .char_indices()
+            .skip(1)
+            .find(|&(_, c)| c == '-')
+            .map(|(i, _)| i)
+            .ok_or_else(|| anyhow!("{:?} isn't of the form start-end", s))?;
+        let start = s[..split]
+            .parse()
+            .map_err(|e| anyhow!("bad start in {:?}: {}", s, e))?;
+        let end = s[split + 1..]
+            .parse()
+            .map_err(|e| anyhow!("bad end in {:?}: {}", s, e))?;
+        Interval::new(start, end)
+    }
+}
+
+/// The smallest list of disjoint intervals covering the same integers, in
+/// order. Intervals that only touch are merged too.
+pub fn union(intervals: impl IntoIterator<Item = Interval>) -> Vec<Interval> {
+    let mut intervals: Vec<Interval> = intervals.into_iter().collect();
+    intervals.sort();
+    let mut merged: Vec<Interval> = Vec::new();
+    for cur in intervals {
+        match merged.last_mut() {
+            Some(last) if cur.start <= last.end.saturating_add(1) => {
+                last.end = last.end.max(cur.end);
+            }
+            _ => merged.push(cur),
+        }
+    }
+    merged
+}
+
+/// The indices of intervals contained in some other interval. Of several
+/// equal intervals, all but the first count as redundant.
+pub fn redundant(intervals: &[Interval]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..intervals.len()).collect();
+    // Wider intervals come first among those with the same start, so
+    // anything containing an interval is seen before it.
+    order.sort_by_key(|&i| {
+        (intervals[i].start, std::cmp::Reverse(intervals[i].end), i)
+    });
+    let mut redundant = Vec::new();
+    let mut reach = None;
+    for i in order {
+        let end = intervals[i].end;
+        if reach.is_some_and(|reach| end <= reach) {
+            redundant.push(i);
+        }
+        reach = reach.max(Some(end));
+    }
+    redundant.sort();
+    redundant
+}
+
+/// Start and end events for a sweep from left to right. An interval's end
+/// event comes just after its last integer, and at any position ends sort
+/// before starts, so intervals that merely touch never overlap.
+fn events(intervals: &[Interval]) -> Vec<(i64, bool, usize)> {
+    let mut events: Vec<_> = intervals
+        .iter()
+        .enumerate()
+        .flat_map(|(i, r)| [(r.start, true, i), (r.end + 1, false, i)])
+        .collect();
+    events.sort();
+    events
+}
+
+/// The largest number of intervals that share a single integer.
+pub fn max_overlap(intervals: &[Interval]) -> usize {
+    let mut active = 0;
+    let mut max = 0;
+    for (_, start, _) in events(intervals) {
+        if start {
+            active += 1;
+            max = max.max(active);
+        } else {
+            active -= 1;
+        }
+    }
+    max
+}
+
+/// Every maximal group of two or more intervals that share at least one
+/// integer, as sorted indices, in the order the sweep finds them.
+pub fn overlapping_groups(intervals: &[Interval]) -> Vec<Vec<usize>> {
+    let mut groups = Vec::new();
+    let mut active = BTreeSet::new();
+    // Whether anything has started since the last interval ended. The
+    // active set is only maximal just before the first end after a start.
+    let mut grew = false;
+    for (_, start, i) in events(intervals) {
+        if start {
+            active.insert(i);
+            grew = true;
+        } else {
+            if grew && active.len() > 1 {
+                groups.push(active.iter().copied().collect());
+            }
+            grew = false;
+            active.remove(&i);
+        }
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn intervals(s: &str) -> Vec<Interval> {
+        s.split(' ').map(|r| r.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        let r: Interval = "-5--2".parse().unwrap();
+        assert_eq!((r.start(), r.end()), (-5, -2));
+        assert_eq!(r.to_string(), "-5--2");
+        assert_eq!(r.len(), 4);
+        assert!("3-2".parse::<Interval>().is_err());
+        assert!("3".parse::<Interval>().is_err());
+        assert!("a-2".parse::<Interval>().is_err());
+    }
+
+    #[test]
+    fn test_pairs() {
+        let [a, b, c] = intervals("2-8 3-7 8-9")[..] else {
+            unreachable!()
+        };
+        assert!(a.contains(&b) && !b.contains(&a));
+        assert!(a.overlaps(&c) && !b.overlaps(&c));
+        assert_eq!(a.intersection(&c), Some(Interval::new(8, 8).unwrap()));
+        assert_eq!(b.intersection(&c), None);
+        assert!(c.contains_point(9) && !c.contains_point(10));
+    }
+
+    #[test]
+    fn test_union() {
+        let merged = union(intervals("5-7 1-2 3-3 9-12 10-11"));
+        assert_eq!(merged, intervals("1-3 5-7 9-12"));
+        assert!(union([]).is_empty());
+    }
+
+    #[test]
+    fn test_redundant() {
+        let r = intervals("2-8 3-7 1-4 3-7 8-8 6-9");
+        assert_eq!(redundant(&r), vec![1, 3, 4]);
+    }
+
+    #[test]
+    fn test_overlaps() {
+        let r = intervals("1-4 2-6 3-3 5-8 9-9 7-10");
+        assert_eq!(max_overlap(&r), 3);
+        assert_eq!(
+            overlapping_groups(&r),
+            vec![vec![0, 1, 2], vec![1, 3], vec![3, 5], vec![4, 5]]
+        );
+        assert_eq!(max_overlap(&intervals("1-2 3-4")), 1);
+        assert!(overlapping_groups(&intervals("1-2 3-4")).is_empty());
+    }
+}
@@ -1 +1,7 @@
+pub mod components;
+pub mod delimiters;
+pub mod dir;
+pub mod interval;
 pub mod pos;
+pub mod rng;
+pub mod top_k;
@@ -40,6 +40,29 @@ impl Pos {
     pub fn distance(&self, other: &Pos) -> u64 {
         self.0.abs_diff(other.0) + self.1.abs_diff(other.1)
     }
+    /// Reflects across the vertical line at `x`.
+    pub fn reflect_x(&self, x: i64) -> Pos {
+        Pos(2 * x - self.0, self.1)
+    }
+    /// Reflects across the horizontal line at `y`.
+    pub fn reflect_y(&self, y: i64) -> Pos {
+        Pos(self.0, 2 * y - self.1)
+    }
+    /// Rotates about the origin by `quarter_turns` 90° turns, from the +x
+    /// axis towards the +y axis. Negative turns go the other way.
+    pub fn rotate(&self, quarter_turns: i32) -> Pos {
+        let &Pos(x, y) = self;
+        match quarter_turns.rem_euclid(4) {
+            0 => Pos(x, y),
+            1 => Pos(-y, x),
+            2 => Pos(-x, -y),
+            _ => Pos(y, -x),
+        }
+    }
+    /// Swaps the x and y coordinates.
+    pub fn transpose(&self) -> Pos {
+        Pos(self.1, self.0)
+    }
     pub fn iter_x(&self, end: i64) -> PosIter {
         assert!(end >= self.0);
         PosIter {
@@ -60,6 +83,46 @@ impl Pos {
     }
 }
 
+/// Geometric transforms over collections of positions. If a mapping sends two
+/// positions to the same place, one of them is lost.
+pub trait Transform: Sized {
+    fn map_pos(self, f: impl Fn(Pos) -> Pos) -> Self;
+
+    fn reflect_x(self, x: i64) -> Self {
+        self.map_pos(|p| p.reflect_x(x))
+    }
+    fn reflect_y(self, y: i64) -> Self {
+        self.map_pos(|p| p.reflect_y(y))
+    }
+    fn rotate(self, quarter_turns: i32) -> Self {
+        self.map_pos(|p| p.rotate(quarter_turns))
+    }
+    fn transpose(self) -> Self {
+        self.map_pos(|p| p.transpose())
+    }
+    fn translate(self, by: Pos) -> Self {
+        self.map_pos(|p| p + by)
+    }
+}
+
+impl Transform for PosSet {
+    fn map_pos(self, f: impl Fn(Pos) -> Pos) -> Self {
+        self.into_iter().map(f).collect()
+    }
+}
+
+impl<T> Transform for PosMap<T> {
+    fn map_pos(self, f: impl Fn(Pos) -> Pos) -> Self {
+        self.into_iter().map(|(p, t)| (f(p), t)).collect()
+    }
+}
+
+impl<T> Transform for DigitGrid<T> {
+    fn map_pos(self, f: impl Fn(Pos) -> Pos) -> Self {
+        DigitGrid(self.0.map_pos(f))
+    }
+}
+
 pub struct PosIter {
     step: Box<dyn Fn(Pos) -> Pos>,
     step_back: Box<dyn Fn(Pos) -> Pos>,
@@ -90,6 +153,7 @@ impl DoubleEndedIterator for PosIter {
     }
 }
 
+#[derive(Debug, Clone)]
 pub struct DigitGrid<T>(pub PosMap<T>);
 impl<T> FromStr for DigitGrid<T>
 where
@@ -113,8 +177,39 @@ where
 }
 
 impl<T> DigitGrid<T> {
+    /// The smallest x and smallest y of any cell, which is the origin for a
+    /// grid as parsed but can move once it's been transformed.
+    pub fn min(&self) -> Pos {
+        let xmin = self.0.keys().map(|p| p.0).min().unwrap();
+        let ymin = self.0.keys().map(|p| p.1).min().unwrap();
+        Pos(xmin, ymin)
+    }
+    /// The largest x and largest y of any cell.
     pub fn max(&self) -> Pos {
-        *self.0.keys().max().unwrap()
+        let xmax = self.0.keys().map(|p| p.0).max().unwrap();
+        let ymax = self.0.keys().map(|p| p.1).max().unwrap();
+        Pos(xmax, ymax)
+    }
+}
+
+/// Renders the grid one row per line, the way it was parsed. A transformed
+/// grid is drawn from its top left cell, wherever that ended up.
+impl<T: Display> Display for DigitGrid<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.0.is_empty() {
+            return Ok(());
+        }
+        let (Pos(xmin, ymin), Pos(xmax, ymax)) = (self.min(), self.max());
+        for y in ymin..=ymax {
+            for x in xmin..=xmax {
+                match self.0.get(&Pos(x, y)) {
+                    Some(d) => write!(f, "{}", d)?,
+                    None => write!(f, " ")?,
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
     }
 }
 
@@ -183,6 +278,13 @@ mod tests {
         assert_eq!("-1,200".parse::<Pos>().unwrap(), Pos(-1, 200));
     }
 
+    #[test]
+    fn test_digit_grid() {
+        let grid: DigitGrid<u8> = "123\n456\n".parse().unwrap();
+        assert_eq!(grid.max(), Pos(2, 1));
+        assert_eq!(grid.to_string(), "123\n456\n");
+    }
+
     #[test]
     fn test_distance() {
         assert_eq!(Pos(-1, -1).distance(&Pos(1, 1)), 4);
@@ -193,6 +295,34 @@ mod tests {
         assert_eq!(Pos(-100, 200) + Pos(100, -200), Pos(0, 0));
     }
 
+    #[test]
+    fn test_transform() {
+        assert_eq!(Pos(1, 5).reflect_x(3), Pos(5, 5));
+        assert_eq!(Pos(1, 5).reflect_y(3), Pos(1, 1));
+        assert_eq!(Pos(2, 1).rotate(1), Pos(-1, 2));
+        assert_eq!(Pos(2, 1).rotate(-1), Pos(1, -2));
+        assert_eq!(Pos(2, 1).rotate(6), Pos(-2, -1));
+        assert_eq!(Pos(2, 1).transpose(), Pos(1, 2));
+
+        let set = PosSet::from([Pos(0, 0), Pos(1, 0)]);
+        assert_eq!(
+            set.clone().rotate(1).translate(Pos(1, 1)),
+            PosSet::from([Pos(1, 1), Pos(1, 2)])
+        );
+        assert_eq!(set.clone().reflect_x(0).reflect_x(0), set);
+
+        let grid: DigitGrid<u8> = "12\n34".parse().unwrap();
+        assert_eq!(grid.clone().transpose().to_string(), "13\n24\n");
+        let rotated = grid.clone().rotate(1);
+        assert_eq!((rotated.min(), rotated.max()), (Pos(-1, 0), Pos(0, 1)));
+        assert_eq!(rotated.to_string(), "31\n42\n");
+        assert_eq!(grid.clone().rotate(2).to_string(), "43\n21\n");
+        assert_eq!(grid.clone().rotate(-1).to_string(), "24\n13\n");
+        assert_eq!(grid.clone().reflect_x(0).to_string(), "21\n43\n");
+        assert_eq!(grid.clone().reflect_y(0).to_string(), "34\n12\n");
+        assert_eq!(grid.translate(Pos(5, -3)).to_string(), "12\n34\n");
+    }
+
     #[test]
     fn test_iter() {
         assert_eq!(
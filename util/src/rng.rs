@@ -0,0 +1,21 @@
+//! A small deterministic pseudo-random generator, so property tests can
+//! throw lots of varied input at the code and still fail reproducibly.
+
+/// A 64-bit linear congruential generator, with Knuth's MMIX constants.
+#[derive(Clone, Debug)]
+pub struct Lcg(u64);
+
+impl Lcg {
+    pub fn new(seed: u64) -> Self {
+        Lcg(seed)
+    }
+
+    /// A number in `0..m`. Only the well mixed high bits are used.
+    pub fn below(&mut self, m: u64) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.0 >> 33) % m
+    }
+}
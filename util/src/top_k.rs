@@ -0,0 +1,92 @@
+//! The largest few items of a stream, kept in a bounded min-heap so memory
+//! doesn't grow with the stream.
+
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+/// The `k` largest items pushed so far.
+#[derive(Clone, Debug)]
+pub struct TopK<T> {
+    k: usize,
+    // The smallest kept item sits on top, ready to be evicted.
+    heap: BinaryHeap<Reverse<T>>,
+}
+
+impl<T: Ord> TopK<T> {
+    pub fn new(k: usize) -> Self {
+        TopK {
+            k,
+            heap: BinaryHeap::with_capacity(k),
+        }
+    }
+
+    pub fn push(&mut self, item: T) {
+        if self.heap.len() < self.k {
+            self.heap.push(Reverse(item));
+        } else if let Some(mut smallest) = self.heap.peek_mut() {
+            if item > smallest.0 {
+                smallest.0 = item;
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    /// The kept items, largest first. There are fewer than `k` if fewer
+    /// were pushed.
+    pub fn into_sorted_vec(self) -> Vec<T> {
+        // Sorting the reversed items ascending puts the largest first.
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|r| r.0)
+            .collect()
+    }
+}
+
+impl<T: Ord> Extend<T> for TopK<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.push(item);
+        }
+    }
+}
+
+/// The `k` largest items of `iter`, largest first.
+pub fn top_k<T: Ord>(iter: impl IntoIterator<Item = T>, k: usize) -> Vec<T> {
+    let mut top = TopK::new(k);
+    top.extend(iter);
+    top.into_sorted_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::Lcg;
+
+    #[test]
+    fn test_top_k() {
+        assert_eq!(top_k([5, 1, 9, 3, 9, 7], 3), vec![9, 9, 7]);
+        assert_eq!(top_k([2, 1], 3), vec![2, 1]);
+        assert!(top_k([1, 2, 3], 0).is_empty());
+        assert!(top_k(Vec::<u8>::new(), 3).is_empty());
+        assert_eq!(top_k("hello".chars(), 2), vec!['o', 'l']);
+    }
+
+    #[test]
+    fn test_matches_sort() {
+        let mut rng = Lcg::new(7);
+        for k in 0..20 {
+            let items: Vec<u64> = (0..100).map(|_| rng.below(50)).collect();
+            let mut sorted = items.clone();
+            sorted.sort_by(|a, b| b.cmp(a));
+            sorted.truncate(k);
+            assert_eq!(top_k(items, k), sorted);
+        }
+    }
+}